@@ -1,16 +1,141 @@
 use crate::ast::TNode;
-use brdgme_color::Style;
+use crate::color;
+use crate::runs::{runs, Attrs};
+use crate::transform::to_lines;
+use brdgme_color::{Color, Style};
+
+/// The colors a terminal supports, from 24 bit color down to none at all.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ColorDepth {
+    TrueColor,
+    /// The xterm 256 color palette.
+    Ansi256,
+    /// The 8 standard colors and their bright variants.
+    Ansi16,
+    Ansi8,
+    /// No colors, only bold, for terminals or users who don't want color.
+    None,
+}
+
+/// The standard xterm values for the 16 basic terminal colors.
+const BASIC: [Color; 16] = [
+    Color { r: 0, g: 0, b: 0 },
+    Color { r: 205, g: 0, b: 0 },
+    Color { r: 0, g: 205, b: 0 },
+    Color { r: 205, g: 205, b: 0 },
+    Color { r: 0, g: 0, b: 238 },
+    Color { r: 205, g: 0, b: 205 },
+    Color { r: 0, g: 205, b: 205 },
+    Color { r: 229, g: 229, b: 229 },
+    Color { r: 127, g: 127, b: 127 },
+    Color { r: 255, g: 0, b: 0 },
+    Color { r: 0, g: 255, b: 0 },
+    Color { r: 255, g: 255, b: 0 },
+    Color { r: 92, g: 92, b: 255 },
+    Color { r: 255, g: 0, b: 255 },
+    Color { r: 0, g: 255, b: 255 },
+    Color { r: 255, g: 255, b: 255 },
+];
+
+/// Channel values used by the 6x6x6 color cube in the 256 color palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The color of an entry in the 256 color palette.
+fn palette_256(i: usize) -> Color {
+    match i {
+        0..=15 => BASIC[i],
+        16..=231 => {
+            let i = i - 16;
+            Color {
+                r: CUBE[i / 36],
+                g: CUBE[i / 6 % 6],
+                b: CUBE[i % 6],
+            }
+        }
+        _ => {
+            let v = (8 + (i - 232) * 10) as u8;
+            Color { r: v, g: v, b: v }
+        }
+    }
+}
+
+thread_local! {
+    /// Lab values of the palettes, which are converted once as `color_code` is
+    /// called for every style change.
+    static BASIC_LAB: Vec<(f64, f64, f64)> = BASIC.iter().map(color::lab).collect();
+    /// Lab values of palette entries 16 to 255, skipping the basic colors
+    /// which terminals often customise.
+    static PALETTE_256_LAB: Vec<(f64, f64, f64)> =
+        (16..256).map(|i| color::lab(&palette_256(i))).collect();
+}
+
+/// The index of the palette entry closest to the given color, with the palette
+/// given as Lab values.
+fn nearest(c: &Color, palette: &[(f64, f64, f64)]) -> usize {
+    let c = color::lab(c);
+    palette
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (i, color::lab_distance(&c, p)))
+        .fold((0, f64::INFINITY), |best, (i, d)| {
+            if d < best.1 {
+                (i, d)
+            } else {
+                best
+            }
+        })
+        .0
+}
+
+/// The SGR parameters for a foreground, or background if `bg` is set.
+fn color_code(c: &Color, bg: bool, depth: ColorDepth) -> Option<String> {
+    let base = if bg { 40 } else { 30 };
+    match depth {
+        ColorDepth::TrueColor => Some(format!("{};2;{};{};{}", base + 8, c.r, c.g, c.b)),
+        ColorDepth::Ansi256 => Some(format!(
+            "{};5;{}",
+            base + 8,
+            PALETTE_256_LAB.with(|p| nearest(c, p)) + 16
+        )),
+        ColorDepth::Ansi16 => Some(match BASIC_LAB.with(|p| nearest(c, p)) {
+            i if i < 8 => format!("{}", base + i),
+            i => format!("{}", base + 60 + i - 8),
+        }),
+        ColorDepth::Ansi8 => Some(format!("{}", base + BASIC_LAB.with(|p| nearest(c, &p[..8])))),
+        ColorDepth::None => None,
+    }
+}
+
+/// The escape sequence which switches the terminal to the given style.
+fn sgr(style: &Style, depth: ColorDepth) -> String {
+    if depth == ColorDepth::TrueColor {
+        return style.ansi();
+    }
+    let mut params = vec!["0".to_string()];
+    if style.bold {
+        params.push("1".to_string());
+    }
+    params.extend(color_code(style.fg, false, depth));
+    params.extend(color_code(style.bg, true, depth));
+    format!("\x1b[{}m", params.join(";"))
+}
 
 pub fn render(input: &[TNode]) -> String {
+    render_depth(input, ColorDepth::TrueColor)
+}
+
+/// Renders using only the colors available at the given depth, mapping each
+/// color to the nearest one in the palette.
+pub fn render_depth(input: &[TNode], depth: ColorDepth) -> String {
     let default_style = Style::default();
     format!(
         "{}{}",
-        default_style.ansi(),
-        render_styled(input, default_style)
+        sgr(&default_style, depth),
+        render_styled(input, default_style, depth)
     )
 }
 
-fn render_styled(input: &[TNode], last_style: Style) -> String {
+fn render_styled(input: &[TNode], last_style: Style, depth: ColorDepth) -> String {
     let mut buf = String::new();
     for n in input {
         match *n {
@@ -20,29 +145,441 @@ fn render_styled(input: &[TNode], last_style: Style) -> String {
                     fg: color,
                     ..last_style
                 };
-                buf.push_str(&new_style.ansi());
-                buf.push_str(&render_styled(children, new_style));
-                buf.push_str(&last_style.ansi());
+                buf.push_str(&sgr(&new_style, depth));
+                buf.push_str(&render_styled(children, new_style, depth));
+                buf.push_str(&sgr(&last_style, depth));
             }
             TNode::Bg(ref color, ref children) => {
                 let new_style = Style {
                     bg: color,
                     ..last_style
                 };
-                buf.push_str(&new_style.ansi());
-                buf.push_str(&render_styled(children, new_style));
-                buf.push_str(&last_style.ansi());
+                buf.push_str(&sgr(&new_style, depth));
+                buf.push_str(&render_styled(children, new_style, depth));
+                buf.push_str(&sgr(&last_style, depth));
             }
             TNode::Bold(ref children) => {
                 let new_style = Style {
                     bold: true,
                     ..last_style
                 };
-                buf.push_str(&new_style.ansi());
-                buf.push_str(&render_styled(children, new_style));
-                buf.push_str(&last_style.ansi());
+                buf.push_str(&sgr(&new_style, depth));
+                buf.push_str(&render_styled(children, new_style, depth));
+                buf.push_str(&sgr(&last_style, depth));
+            }
+        }
+    }
+    buf
+}
+
+/// The SGR parameters needed to move the terminal from one set of
+/// attributes to another.  Foreground and bold are left alone for text which
+/// is only whitespace, as they aren't visible.
+fn delta(from: &Attrs, to: &Attrs, whitespace: bool, depth: ColorDepth) -> Vec<String> {
+    let mut params = vec![];
+    if to.bold != from.bold && !whitespace {
+        params.push(if to.bold { "1" } else { "22" }.to_string());
+    }
+    if to.fg != from.fg && !whitespace {
+        params.extend(to.fg.and_then(|c| color_code(&c, false, depth)));
+    }
+    if to.bg != from.bg {
+        params.extend(to.bg.and_then(|c| color_code(&c, true, depth)));
+    }
+    params
+}
+
+/// Renders with as few escape codes as possible by tracking the terminal
+/// state and only emitting the attributes which change between runs of text.
+/// The terminal is reset at the end so styles don't leak into later output.
+pub fn render_minimal(input: &[TNode], depth: ColorDepth) -> String {
+    // Colors are always set, as unstyled text uses the default style.
+    let default_attrs = Attrs::from(Style::default());
+    let mut rs = vec![];
+    runs(input, default_attrs, &mut rs);
+    let mut buf = String::new();
+    let mut current: Option<Attrs> = None;
+    for (attrs, text) in rs {
+        let whitespace = text.chars().all(char::is_whitespace);
+        let params = match current {
+            // Start from a known state.
+            None => {
+                let mut params = vec!["0".to_string()];
+                if attrs.bold {
+                    params.push("1".to_string());
+                }
+                params.extend(attrs.fg.and_then(|c| color_code(&c, false, depth)));
+                params.extend(attrs.bg.and_then(|c| color_code(&c, true, depth)));
+                current = Some(attrs);
+                params
+            }
+            Some(ref mut cur) => {
+                let params = delta(cur, &attrs, whitespace, depth);
+                cur.bg = attrs.bg;
+                if !whitespace {
+                    *cur = attrs;
+                }
+                params
             }
+        };
+        if !params.is_empty() {
+            buf.push_str(&format!("\x1b[{}m", params.join(";")));
         }
+        buf.push_str(&text);
+    }
+    if current.is_some() {
+        buf.push_str("\x1b[0m");
     }
     buf
 }
+
+/// Renders each line separately, so every line sets up its own styles and
+/// resets them at the end.  Lines can be printed or paged independently
+/// without styles bleeding between them.
+pub fn render_lines(input: &[TNode], depth: ColorDepth) -> Vec<String> {
+    to_lines(input)
+        .iter()
+        .map(|l| render_minimal(l, depth))
+        .collect()
+}
+
+/// Renders with styles reset before each newline and reapplied after it, for
+/// use with pagers such as `less -R`.
+pub fn render_paged(input: &[TNode], depth: ColorDepth) -> String {
+    render_lines(input, depth).join("\n")
+}
+
+/// Attributes set by escape codes while importing, where `None` colors are the
+/// terminal defaults.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+struct ParseAttrs {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+}
+
+impl ParseAttrs {
+    fn node(&self, text: String) -> TNode {
+        let mut n = TNode::Text(text);
+        if self.bold {
+            n = TNode::Bold(vec![n]);
+        }
+        if let Some(bg) = self.bg {
+            n = TNode::Bg(bg, vec![n]);
+        }
+        if let Some(fg) = self.fg {
+            n = TNode::Fg(fg, vec![n]);
+        }
+        n
+    }
+
+    /// Applies the parameters of an SGR escape code.
+    fn apply(&mut self, params: &[usize]) {
+        if params.is_empty() {
+            *self = ParseAttrs::default();
+            return;
+        }
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => *self = ParseAttrs::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                p @ 30..=37 => self.fg = Some(BASIC[p - 30]),
+                p @ 90..=97 => self.fg = Some(BASIC[p - 90 + 8]),
+                39 => self.fg = None,
+                p @ 40..=47 => self.bg = Some(BASIC[p - 40]),
+                p @ 100..=107 => self.bg = Some(BASIC[p - 100 + 8]),
+                49 => self.bg = None,
+                p @ 38 | p @ 48 => {
+                    let c = match params.get(i + 1) {
+                        Some(&5) => params.get(i + 2).map(|&n| {
+                            i += 2;
+                            palette_256(n.min(255))
+                        }),
+                        Some(&2) if params.len() > i + 4 => {
+                            let channel = |n: usize| n.min(255) as u8;
+                            let c = Color {
+                                r: channel(params[i + 2]),
+                                g: channel(params[i + 3]),
+                                b: channel(params[i + 4]),
+                            };
+                            i += 4;
+                            Some(c)
+                        }
+                        _ => None,
+                    };
+                    if c.is_some() {
+                        if p == 38 {
+                            self.fg = c;
+                        } else {
+                            self.bg = c;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        // Rendered output sets the default style explicitly, so those colors
+        // are left unset to import as unstyled text.
+        let default_style = Style::default();
+        if self.fg == Some(*default_style.fg) {
+            self.fg = None;
+        }
+        if self.bg == Some(*default_style.bg) {
+            self.bg = None;
+        }
+    }
+}
+
+/// Imports text containing ANSI escape codes, such as previously rendered
+/// output, converting SGR codes for colors and bold into nodes.  Other escape
+/// codes are dropped.
+pub fn parse(input: &str) -> Vec<TNode> {
+    let mut runs: Vec<(ParseAttrs, String)> = vec![];
+    let mut attrs = ParseAttrs::default();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            match runs.last_mut() {
+                Some(&mut (last, ref mut text)) if last == attrs => text.push(c),
+                _ => runs.push((attrs, c.to_string())),
+            }
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {}
+            // String sequences, such as OSC window titles, run until the
+            // string terminator ESC \ or a BEL.
+            Some(']') | Some('P') | Some('X') | Some('^') | Some('_') => {
+                while let Some(sc) = chars.next() {
+                    if sc == '\x07' {
+                        break;
+                    }
+                    if sc == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+                continue;
+            }
+            // Other escapes are any intermediate bytes followed by one final
+            // byte, such as ESC ( B to select a character set.
+            Some(sc) => {
+                if (' '..='/').contains(&sc) {
+                    for sc in chars.by_ref() {
+                        if !(' '..='/').contains(&sc) {
+                            break;
+                        }
+                    }
+                }
+                continue;
+            }
+            None => continue,
+        }
+        let mut seq = String::new();
+        let mut terminator = None;
+        for sc in chars.by_ref() {
+            // The final byte of a control sequence is in the range @ to ~.
+            if ('@'..='~').contains(&sc) {
+                terminator = Some(sc);
+                break;
+            }
+            seq.push(sc);
+        }
+        if terminator == Some('m') {
+            // Empty parameters default to 0, as in terminals.
+            let params: Vec<usize> = seq.split(';')
+                .filter_map(|p| if p.is_empty() { Some(0) } else { p.parse().ok() })
+                .collect();
+            attrs.apply(&params);
+        }
+    }
+    runs.into_iter()
+        .map(|(attrs, text)| attrs.node(text))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_256_works() {
+        assert_eq!(BASIC[9], palette_256(9));
+        assert_eq!(Color { r: 0, g: 0, b: 0 }, palette_256(16));
+        assert_eq!(Color { r: 255, g: 135, b: 0 }, palette_256(208));
+        assert_eq!(Color { r: 255, g: 255, b: 255 }, palette_256(231));
+        assert_eq!(Color { r: 8, g: 8, b: 8 }, palette_256(232));
+        assert_eq!(Color { r: 238, g: 238, b: 238 }, palette_256(255));
+    }
+
+    #[test]
+    fn render_minimal_works() {
+        let red = Color {
+            r: 250,
+            g: 10,
+            b: 10,
+        };
+        let input = vec![
+            TNode::text("a"),
+            TNode::Fg(
+                red,
+                vec![
+                    TNode::text("b"),
+                    TNode::Bold(vec![TNode::text("c")]),
+                    TNode::Bg(red, vec![TNode::text("  ")]),
+                    TNode::text(" d"),
+                ],
+            ),
+            TNode::text("e"),
+        ];
+        assert_eq!(
+            "\x1b[0;30;107ma\x1b[91mb\x1b[1mc\x1b[101m  \x1b[22;107m d\x1b[30me\x1b[0m",
+            render_minimal(&input, ColorDepth::Ansi16)
+        );
+        assert_eq!(
+            "\x1b[0mab\x1b[1mc  \x1b[22m de\x1b[0m",
+            render_minimal(&input, ColorDepth::None)
+        );
+        assert_eq!("", render_minimal(&[], ColorDepth::Ansi16));
+        // Output ending inside a styled node still leaves the terminal reset.
+        assert_eq!(
+            "\x1b[0;30;107ma\x1b[1;91mb\x1b[0m",
+            render_minimal(
+                &[TNode::text("a"), TNode::Bold(vec![TNode::Fg(red, vec![TNode::text("b")])])],
+                ColorDepth::Ansi16
+            )
+        );
+    }
+
+    #[test]
+    fn render_lines_works() {
+        let input = vec![
+            TNode::Bold(vec![TNode::text("a\nb")]),
+            TNode::text("\n\nc"),
+        ];
+        assert_eq!(
+            vec![
+                "\x1b[0;1;30;107ma\x1b[0m",
+                "\x1b[0;1;30;107mb\x1b[0m",
+                "",
+                "\x1b[0;30;107mc\x1b[0m",
+            ],
+            render_lines(&input, ColorDepth::Ansi16)
+        );
+        assert_eq!(
+            "\x1b[0;1ma\x1b[0m\n\x1b[0;1mb\x1b[0m\n\n\x1b[0mc\x1b[0m",
+            render_paged(&input, ColorDepth::None)
+        );
+    }
+
+    #[test]
+    fn parse_works() {
+        assert_eq!(
+            vec![
+                TNode::text("a "),
+                TNode::Fg(BASIC[1], vec![TNode::Bold(vec![TNode::text("b")])]),
+                TNode::Fg(
+                    BASIC[1],
+                    vec![TNode::Bg(palette_256(208), vec![TNode::text("c")])],
+                ),
+                TNode::Bg(
+                    Color {
+                        r: 1,
+                        g: 2,
+                        b: 3,
+                    },
+                    vec![TNode::text("d")],
+                ),
+                TNode::text("e"),
+            ],
+            parse(
+                "a \x1b[1;31mb\x1b[22;48;5;208mc\x1b[39;48;2;1;2;3md\x1b[2K\x1b[me",
+            )
+        );
+        let input = vec![
+            TNode::Fg(
+                Color {
+                    r: 10,
+                    g: 20,
+                    b: 30,
+                },
+                vec![TNode::Bold(vec![TNode::text("a\nb")])],
+            ),
+        ];
+        assert_eq!(
+            render_minimal(&input, ColorDepth::TrueColor),
+            render_minimal(
+                &parse(&render_minimal(&input, ColorDepth::TrueColor)),
+                ColorDepth::TrueColor
+            )
+        );
+        // Empty parameters are zero, both as a reset and inside colors.
+        assert_eq!(
+            vec![
+                TNode::Fg(BASIC[1], vec![TNode::Bold(vec![TNode::text("a")])]),
+                TNode::Fg(BASIC[2], vec![TNode::text("b")]),
+                TNode::Bg(
+                    Color {
+                        r: 0,
+                        g: 128,
+                        b: 255,
+                    },
+                    vec![TNode::text("c")],
+                ),
+            ],
+            parse("\x1b[1;31ma\x1b[;32mb\x1b[0;48;2;;128;255mc")
+        );
+        // Default colors set by the renderers import as unstyled text.
+        let input = vec![
+            TNode::text("a"),
+            TNode::Fg(BASIC[1], vec![TNode::Bold(vec![TNode::text("b")])]),
+            TNode::Bg(BASIC[4], vec![TNode::text("c")]),
+        ];
+        assert_eq!(input, parse(&render(&input)));
+        assert_eq!(input, parse(&render_minimal(&input, ColorDepth::TrueColor)));
+        // 21 is double underline in many terminals, so doesn't end bold.
+        assert_eq!(
+            vec![TNode::Bold(vec![TNode::text("ab")])],
+            parse("\x1b[1ma\x1b[21mb")
+        );
+        // Escapes other than CSI are skipped without leaking their payloads.
+        assert_eq!(
+            vec![TNode::text("abc")],
+            parse("\x1b]0;title\x07a\x1b]8;;http://x\x1b\\b\x1b(Bc\x1b")
+        );
+    }
+
+    #[test]
+    fn render_depth_works() {
+        let input = vec![
+            TNode::Fg(
+                Color {
+                    r: 250,
+                    g: 10,
+                    b: 10,
+                },
+                vec![TNode::Bold(vec![TNode::text("a")])],
+            ),
+        ];
+        assert_eq!(render(&input), render_depth(&input, ColorDepth::TrueColor));
+        assert_eq!(
+            "\x1b[0;30;107m\x1b[0;91;107m\x1b[0;1;91;107ma\x1b[0;91;107m\x1b[0;30;107m",
+            render_depth(&input, ColorDepth::Ansi16)
+        );
+        assert_eq!(
+            "\x1b[0;30;47m\x1b[0;31;47m\x1b[0;1;31;47ma\x1b[0;31;47m\x1b[0;30;47m",
+            render_depth(&input, ColorDepth::Ansi8)
+        );
+        assert!(
+            render_depth(&input, ColorDepth::Ansi256)
+                .starts_with("\x1b[0;38;5;16;48;5;231m\x1b[0;38;5;196;48;5;231m")
+        );
+        assert_eq!(
+            "\x1b[0m\x1b[0m\x1b[0;1ma\x1b[0m\x1b[0m",
+            render_depth(&input, ColorDepth::None)
+        );
+    }
+}
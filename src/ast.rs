@@ -1,6 +1,6 @@
 use serde_derive::{Serialize, Deserialize};
 
-use brdgme_color::Color;
+use brdgme_color::{Color, RED};
 
 use std::str::FromStr;
 
@@ -36,16 +36,462 @@ impl FromStr for Align {
     }
 }
 
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+    /// Rotate 180°, which is both a horizontal and vertical flip.
+    Rotate,
+}
+
+impl Flip {
+    pub fn to_string(&self) -> String {
+        match *self {
+            Flip::Horizontal => "horizontal",
+            Flip::Vertical => "vertical",
+            Flip::Rotate => "rotate",
+        }.to_string()
+    }
+}
+
+impl FromStr for Flip {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "horizontal" => Ok(Flip::Horizontal),
+            "vertical" => Ok(Flip::Vertical),
+            "rotate" => Ok(Flip::Rotate),
+            _ => Err(format!(
+                "invalid flip {}, must be one of horizontal, vertical, rotate",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum BoardLabels {
+    None,
+    /// Files lettered from `a` along the bottom, ranks numbered from 1 at the
+    /// bottom.  Files past `z` are labelled with their number instead, so
+    /// boards wider than 26 columns are better off with `Numeric`.
+    Chess,
+    /// Columns numbered from 1 along the bottom, rows numbered from 1 at the
+    /// top.
+    Numeric,
+}
+
+impl BoardLabels {
+    pub fn to_string(&self) -> String {
+        match *self {
+            BoardLabels::None => "none",
+            BoardLabels::Chess => "chess",
+            BoardLabels::Numeric => "numeric",
+        }.to_string()
+    }
+}
+
+impl FromStr for BoardLabels {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(BoardLabels::None),
+            "chess" => Ok(BoardLabels::Chess),
+            "numeric" => Ok(BoardLabels::Numeric),
+            _ => Err(format!(
+                "invalid board labels {}, must be one of none, chess, numeric",
+                s
+            )),
+        }
+    }
+}
+
+/// A checkered square grid, with the top left cell using the light color.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Board {
+    pub rows: Vec<Row>,
+    pub cell_width: usize,
+    pub cell_height: usize,
+    pub light: Col,
+    pub dark: Col,
+    pub labels: BoardLabels,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum HexOrientation {
+    /// Pointy topped hexes, laid out in offset rows with odd rows shifted
+    /// right.
+    Pointy,
+    /// Flat topped hexes, laid out in offset columns with odd columns shifted
+    /// down.
+    Flat,
+}
+
+impl HexOrientation {
+    pub fn to_string(&self) -> String {
+        match *self {
+            HexOrientation::Pointy => "pointy",
+            HexOrientation::Flat => "flat",
+        }.to_string()
+    }
+
+    /// Converts axial coordinates to the offset coordinates used by `Hex`,
+    /// returning `None` if they would be off the top or left of the grid.
+    pub fn offset(&self, q: isize, r: isize) -> Option<(usize, usize)> {
+        let (col, row) = match *self {
+            HexOrientation::Pointy => (q + (r - (r & 1)) / 2, r),
+            HexOrientation::Flat => (q, r + (q - (q & 1)) / 2),
+        };
+        if col < 0 || row < 0 {
+            return None;
+        }
+        Some((col as usize, row as usize))
+    }
+}
+
+impl FromStr for HexOrientation {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pointy" => Ok(HexOrientation::Pointy),
+            "flat" => Ok(HexOrientation::Flat),
+            _ => Err(format!(
+                "invalid hex orientation {}, must be one of pointy, flat",
+                s
+            )),
+        }
+    }
+}
+
+/// A single tile in a hex grid, positioned using offset coordinates.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Hex {
+    pub col: usize,
+    pub row: usize,
+    pub fill: Option<Col>,
+    pub border: Option<Col>,
+    pub content: Vec<Node>,
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Suit {
+    Hearts,
+    Diamonds,
+    Clubs,
+    Spades,
+}
+
+impl Suit {
+    pub fn to_string(&self) -> String {
+        match *self {
+            Suit::Hearts => "hearts",
+            Suit::Diamonds => "diamonds",
+            Suit::Clubs => "clubs",
+            Suit::Spades => "spades",
+        }.to_string()
+    }
+
+    pub fn symbol(&self) -> char {
+        match *self {
+            Suit::Hearts => '♥',
+            Suit::Diamonds => '♦',
+            Suit::Clubs => '♣',
+            Suit::Spades => '♠',
+        }
+    }
+
+    pub fn letter(&self) -> char {
+        match *self {
+            Suit::Hearts => 'H',
+            Suit::Diamonds => 'D',
+            Suit::Clubs => 'C',
+            Suit::Spades => 'S',
+        }
+    }
+
+    /// Black suits use the theme's text color so they stay readable on dark
+    /// backgrounds.
+    pub fn col(&self) -> Col {
+        match *self {
+            Suit::Hearts | Suit::Diamonds => RED.into(),
+            Suit::Clubs | Suit::Spades => ColType::Theme(ThemeRole::Text).into(),
+        }
+    }
+}
+
+impl FromStr for Suit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hearts" => Ok(Suit::Hearts),
+            "diamonds" => Ok(Suit::Diamonds),
+            "clubs" => Ok(Suit::Clubs),
+            "spades" => Ok(Suit::Spades),
+            _ => Err(format!(
+                "invalid suit {}, must be one of hearts, diamonds, clubs, spades",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum GlyphStyle {
+    /// A single inline glyph, such as `Q♥` or `⚄`.
+    Compact,
+    /// Multi-line box art.
+    Large,
+    /// ASCII only, such as `QH` or `[5]`, for plain text clients.
+    Text,
+}
+
+impl GlyphStyle {
+    pub fn to_string(&self) -> String {
+        match *self {
+            GlyphStyle::Compact => "compact",
+            GlyphStyle::Large => "large",
+            GlyphStyle::Text => "text",
+        }.to_string()
+    }
+}
+
+impl FromStr for GlyphStyle {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(GlyphStyle::Compact),
+            "large" => Ok(GlyphStyle::Large),
+            "text" => Ok(GlyphStyle::Text),
+            _ => Err(format!(
+                "invalid glyph style {}, must be one of compact, large, text",
+                s
+            )),
+        }
+    }
+}
+
+/// A meter showing `value` out of `max`, drawn `width` characters wide with
+/// eighth block precision.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct Bar {
+    pub value: usize,
+    pub max: usize,
+    pub width: usize,
+    pub fill: Col,
+    pub empty: Col,
+    /// Draws the bar using ASCII `#` and `-` only, for plain text clients.
+    /// Text bars are wrapped in brackets, which count towards the width.
+    pub text: bool,
+}
+
+impl Bar {
+    pub fn new(value: usize, max: usize, width: usize) -> Bar {
+        Bar {
+            value,
+            max,
+            width,
+            fill: ColType::Theme(ThemeRole::Success).into(),
+            empty: ColType::Theme(ThemeRole::Muted).into(),
+            text: false,
+        }
+    }
+}
+
+/// A single labeled bar in a bar chart.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ChartBar {
+    pub label: Vec<Node>,
+    pub value: i64,
+    pub color: Col,
+}
+
+/// The grammatical form of a player reference, used when referring to the
+/// viewer as "you".
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum Form {
+    Subject,
+    Possessive,
+}
+
+/// Overrides applied to the player style for a single reference.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum PlayerOpt {
+    /// Abbreviate the name.
+    Short,
+    /// Use the initials of the name.
+    Initials,
+    /// No brackets or bold.
+    Plain,
+    /// Prefix the player's symbol.
+    Icon,
+}
+
+impl PlayerOpt {
+    pub fn to_string(&self) -> String {
+        match *self {
+            PlayerOpt::Short => "short",
+            PlayerOpt::Initials => "initials",
+            PlayerOpt::Plain => "plain",
+            PlayerOpt::Icon => "icon",
+        }.to_string()
+    }
+}
+
+impl FromStr for PlayerOpt {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "short" => Ok(PlayerOpt::Short),
+            "initials" => Ok(PlayerOpt::Initials),
+            "plain" => Ok(PlayerOpt::Plain),
+            "icon" => Ok(PlayerOpt::Icon),
+            _ => Err(format!(
+                "invalid player option {}, must be one of short, initials, plain, icon",
+                s
+            )),
+        }
+    }
+}
+
+/// A player reference with a specific form or style, written with tag
+/// arguments such as `{{player 1 possessive cap}}` or `{{player 2 short}}`.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerRef {
+    pub player: usize,
+    pub form: Form,
+    /// Capitalize the reference when it is rendered as "you", for use at the
+    /// start of a sentence.
+    pub cap: bool,
+    pub opts: Vec<PlayerOpt>,
+}
+
+impl PlayerRef {
+    pub fn new(player: usize) -> PlayerRef {
+        PlayerRef {
+            player,
+            form: Form::Subject,
+            cap: false,
+            opts: vec![],
+        }
+    }
+
+    pub fn opt(mut self, opt: PlayerOpt) -> Self {
+        self.opts.push(opt);
+        self
+    }
+
+    pub fn possessive(mut self) -> Self {
+        self.form = Form::Possessive;
+        self
+    }
+
+    pub fn cap(mut self) -> Self {
+        self.cap = true;
+        self
+    }
+
+    pub fn markup_args(&self) -> String {
+        let mut args = self.player.to_string();
+        if self.form == Form::Possessive {
+            args.push_str(" possessive");
+        }
+        if self.cap {
+            args.push_str(" cap");
+        }
+        for opt in &self.opts {
+            args.push_str(&format!(" {}", opt.to_string()));
+        }
+        args
+    }
+}
+
+/// Semantic colors which are resolved using the theme passed to `transform`.
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ThemeRole {
+    Text,
+    Muted,
+    Highlight,
+    Success,
+    Warning,
+    Danger,
+    Info,
+}
+
+impl ThemeRole {
+    pub fn to_string(&self) -> String {
+        match *self {
+            ThemeRole::Text => "text",
+            ThemeRole::Muted => "muted",
+            ThemeRole::Highlight => "highlight",
+            ThemeRole::Success => "success",
+            ThemeRole::Warning => "warning",
+            ThemeRole::Danger => "danger",
+            ThemeRole::Info => "info",
+        }.to_string()
+    }
+}
+
+impl FromStr for ThemeRole {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ThemeRole::Text),
+            "muted" => Ok(ThemeRole::Muted),
+            "highlight" => Ok(ThemeRole::Highlight),
+            "success" => Ok(ThemeRole::Success),
+            "warning" => Ok(ThemeRole::Warning),
+            "danger" => Ok(ThemeRole::Danger),
+            "info" => Ok(ThemeRole::Info),
+            _ => Err(format!(
+                "invalid theme role {}, must be one of text, muted, highlight, success, \
+                 warning, danger, info",
+                s
+            )),
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ColTrans {
     Mono,
     Inv,
+    /// Mix with white by the given percentage.
+    Lighten(u8),
+    /// Mix with black by the given percentage.
+    Darken(u8),
+    /// Mix with another color by the given percentage.
+    Mix(ColType, u8),
+    /// Opacity as a percentage, blended over the background.
+    Alpha(u8),
+    /// Lighten or darken until readable against the background.
+    Contrast,
 }
 
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum ColType {
     RGB(Color),
     Player(usize),
+    Team(usize),
+    /// A color name such as `red`, kept as written so it survives a round
+    /// trip through markup.
+    Named(String),
+    /// A hex color such as `f80` or `ff8800`, kept as written without the
+    /// leading `#`.
+    Hex(String),
+    Theme(ThemeRole),
+}
+
+impl ColType {
+    pub fn markup_args(&self) -> String {
+        match *self {
+            ColType::RGB(c) => format!("rgb({},{},{})", c.r, c.g, c.b),
+            ColType::Player(p) => format!("player({})", p),
+            ColType::Team(t) => format!("team({})", t),
+            ColType::Named(ref n) => n.to_string(),
+            ColType::Hex(ref h) => format!("#{}", h),
+            ColType::Theme(ref r) => format!("theme({})", r.to_string()),
+        }
+    }
 }
 
 #[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
@@ -58,7 +504,7 @@ impl Col {
     pub fn markup_args(&self) -> String {
         format!(
             "{}{}",
-            self.markup_col_type(),
+            self.color.markup_args(),
             match self.transform.len() {
                 0 => "".to_string(),
                 _ => format!(" | {}", self.markup_trans()),
@@ -66,19 +512,17 @@ impl Col {
         )
     }
 
-    fn markup_col_type(&self) -> String {
-        match self.color {
-            ColType::RGB(c) => format!("rgb({},{},{})", c.r, c.g, c.b),
-            ColType::Player(p) => format!("player({})", p),
-        }
-    }
-
     fn markup_trans(&self) -> String {
         self.transform
             .iter()
             .map(|t| match *t {
                 ColTrans::Mono => "mono".to_string(),
                 ColTrans::Inv => "inv".to_string(),
+                ColTrans::Lighten(n) => format!("lighten({})", n),
+                ColTrans::Darken(n) => format!("darken({})", n),
+                ColTrans::Mix(ref ct, n) => format!("mix({},{})", ct.markup_args(), n),
+                ColTrans::Alpha(n) => format!("alpha({})", n),
+                ColTrans::Contrast => "contrast".to_string(),
             })
             .collect::<Vec<String>>()
             .join(" | ")
@@ -95,6 +539,36 @@ impl Col {
         new.transform.push(ColTrans::Mono);
         new
     }
+
+    pub fn lighten(&self, percent: u8) -> Self {
+        let mut new = self.clone();
+        new.transform.push(ColTrans::Lighten(percent));
+        new
+    }
+
+    pub fn darken(&self, percent: u8) -> Self {
+        let mut new = self.clone();
+        new.transform.push(ColTrans::Darken(percent));
+        new
+    }
+
+    pub fn mix(&self, color: ColType, percent: u8) -> Self {
+        let mut new = self.clone();
+        new.transform.push(ColTrans::Mix(color, percent));
+        new
+    }
+
+    pub fn alpha(&self, percent: u8) -> Self {
+        let mut new = self.clone();
+        new.transform.push(ColTrans::Alpha(percent));
+        new
+    }
+
+    pub fn contrast(&self) -> Self {
+        let mut new = self.clone();
+        new.transform.push(ColTrans::Contrast);
+        new
+    }
 }
 
 impl From<usize> for Col {
@@ -106,6 +580,15 @@ impl From<usize> for Col {
     }
 }
 
+impl From<ColType> for Col {
+    fn from(c: ColType) -> Col {
+        Col {
+            color: c,
+            transform: vec![],
+        }
+    }
+}
+
 impl From<Color> for Col {
     fn from(c: Color) -> Col {
         Col {
@@ -123,10 +606,34 @@ pub enum Node {
     Bold(Vec<Node>),
     Text(String),
     Player(usize),
+    PlayerRef(PlayerRef),
+    Team(usize),
     Table(Vec<Row>),
     Align(Align, usize, Vec<Node>),
     Indent(usize, Vec<Node>),
     Canvas(Vec<(usize, usize, Vec<Node>)>),
+    /// A canvas which merges overlapping box drawing characters into
+    /// junctions, so grids can be assembled from individual cell boxes.
+    MergeCanvas(Vec<(usize, usize, Vec<Node>)>),
+    /// Flips the rendered block, preserving styling.  If the flag is set,
+    /// directional glyphs such as arrows and box corners are mirrored too.
+    Flip(Flip, bool, Vec<Node>),
+    Board(Board),
+    HexGrid(HexOrientation, Vec<Hex>),
+    /// A playing card with a rank such as `Q` or `10`.
+    Card(String, Suit, GlyphStyle),
+    Die(usize, GlyphStyle),
+    Bar(Bar),
+    Sparkline(Vec<i64>),
+    /// A horizontal bar chart, with bars up to the given width.
+    BarChart(usize, Vec<ChartBar>),
+    /// Content only visible to the given players.
+    Only(Vec<usize>, Vec<Node>),
+    /// Content visible to everyone except the given players.
+    Except(Vec<usize>, Vec<Node>),
+    /// Colors content with a gradient between two colors, running across
+    /// each line for foregrounds or down the lines for backgrounds if set.
+    Gradient(Col, Col, bool, Vec<Node>),
 }
 
 impl Node {
@@ -250,6 +757,20 @@ mod tests {
     use super::*;
     use brdgme_color::*;
 
+    #[test]
+    fn hex_offset_works() {
+        let pointy = HexOrientation::Pointy;
+        assert_eq!(Some((0, 0)), pointy.offset(0, 0));
+        assert_eq!(Some((1, 1)), pointy.offset(1, 1));
+        assert_eq!(Some((0, 2)), pointy.offset(-1, 2));
+        assert_eq!(None, pointy.offset(-1, 0));
+        let flat = HexOrientation::Flat;
+        assert_eq!(Some((1, 0)), flat.offset(1, 0));
+        assert_eq!(Some((2, 0)), flat.offset(2, -1));
+        assert_eq!(Some((3, 2)), flat.offset(3, 1));
+        assert_eq!(None, flat.offset(0, -1));
+    }
+
     #[test]
     fn bg_ranges_works() {
         assert_eq!(
@@ -0,0 +1,201 @@
+/// Line weights for each arm of a box drawing character, in the order up,
+/// right, down, left.  0 is no line, 1 light, 2 heavy and 3 double.
+pub type Arms = [u8; 4];
+
+const UP: usize = 0;
+const RIGHT: usize = 1;
+const DOWN: usize = 2;
+const LEFT: usize = 3;
+
+/// Box drawing characters with their arms.  When composing arms back into a
+/// character the first match wins, so the rounded and half line variants are
+/// listed last.
+const BOXES: &[(char, Arms)] = &[
+    ('─', [0, 1, 0, 1]),
+    ('━', [0, 2, 0, 2]),
+    ('│', [1, 0, 1, 0]),
+    ('┃', [2, 0, 2, 0]),
+    ('┌', [0, 1, 1, 0]),
+    ('┍', [0, 2, 1, 0]),
+    ('┎', [0, 1, 2, 0]),
+    ('┏', [0, 2, 2, 0]),
+    ('┐', [0, 0, 1, 1]),
+    ('┑', [0, 0, 1, 2]),
+    ('┒', [0, 0, 2, 1]),
+    ('┓', [0, 0, 2, 2]),
+    ('└', [1, 1, 0, 0]),
+    ('┕', [1, 2, 0, 0]),
+    ('┖', [2, 1, 0, 0]),
+    ('┗', [2, 2, 0, 0]),
+    ('┘', [1, 0, 0, 1]),
+    ('┙', [1, 0, 0, 2]),
+    ('┚', [2, 0, 0, 1]),
+    ('┛', [2, 0, 0, 2]),
+    ('├', [1, 1, 1, 0]),
+    ('┝', [1, 2, 1, 0]),
+    ('┞', [2, 1, 1, 0]),
+    ('┟', [1, 1, 2, 0]),
+    ('┠', [2, 1, 2, 0]),
+    ('┡', [2, 2, 1, 0]),
+    ('┢', [1, 2, 2, 0]),
+    ('┣', [2, 2, 2, 0]),
+    ('┤', [1, 0, 1, 1]),
+    ('┥', [1, 0, 1, 2]),
+    ('┦', [2, 0, 1, 1]),
+    ('┧', [1, 0, 2, 1]),
+    ('┨', [2, 0, 2, 1]),
+    ('┩', [2, 0, 1, 2]),
+    ('┪', [1, 0, 2, 2]),
+    ('┫', [2, 0, 2, 2]),
+    ('┬', [0, 1, 1, 1]),
+    ('┭', [0, 1, 1, 2]),
+    ('┮', [0, 2, 1, 1]),
+    ('┯', [0, 2, 1, 2]),
+    ('┰', [0, 1, 2, 1]),
+    ('┱', [0, 1, 2, 2]),
+    ('┲', [0, 2, 2, 1]),
+    ('┳', [0, 2, 2, 2]),
+    ('┴', [1, 1, 0, 1]),
+    ('┵', [1, 1, 0, 2]),
+    ('┶', [1, 2, 0, 1]),
+    ('┷', [1, 2, 0, 2]),
+    ('┸', [2, 1, 0, 1]),
+    ('┹', [2, 1, 0, 2]),
+    ('┺', [2, 2, 0, 1]),
+    ('┻', [2, 2, 0, 2]),
+    ('┼', [1, 1, 1, 1]),
+    ('┽', [1, 1, 1, 2]),
+    ('┾', [1, 2, 1, 1]),
+    ('┿', [1, 2, 1, 2]),
+    ('╀', [2, 1, 1, 1]),
+    ('╁', [1, 1, 2, 1]),
+    ('╂', [2, 1, 2, 1]),
+    ('╃', [2, 1, 1, 2]),
+    ('╄', [2, 2, 1, 1]),
+    ('╅', [1, 1, 2, 2]),
+    ('╆', [1, 2, 2, 1]),
+    ('╇', [2, 2, 1, 2]),
+    ('╈', [1, 2, 2, 2]),
+    ('╉', [2, 1, 2, 2]),
+    ('╊', [2, 2, 2, 1]),
+    ('╋', [2, 2, 2, 2]),
+    ('═', [0, 3, 0, 3]),
+    ('║', [3, 0, 3, 0]),
+    ('╒', [0, 3, 1, 0]),
+    ('╓', [0, 1, 3, 0]),
+    ('╔', [0, 3, 3, 0]),
+    ('╕', [0, 0, 1, 3]),
+    ('╖', [0, 0, 3, 1]),
+    ('╗', [0, 0, 3, 3]),
+    ('╘', [1, 3, 0, 0]),
+    ('╙', [3, 1, 0, 0]),
+    ('╚', [3, 3, 0, 0]),
+    ('╛', [1, 0, 0, 3]),
+    ('╜', [3, 0, 0, 1]),
+    ('╝', [3, 0, 0, 3]),
+    ('╞', [1, 3, 1, 0]),
+    ('╟', [3, 1, 3, 0]),
+    ('╠', [3, 3, 3, 0]),
+    ('╡', [1, 0, 1, 3]),
+    ('╢', [3, 0, 3, 1]),
+    ('╣', [3, 0, 3, 3]),
+    ('╤', [0, 3, 1, 3]),
+    ('╥', [0, 1, 3, 1]),
+    ('╦', [0, 3, 3, 3]),
+    ('╧', [1, 3, 0, 3]),
+    ('╨', [3, 1, 0, 1]),
+    ('╩', [3, 3, 0, 3]),
+    ('╪', [1, 3, 1, 3]),
+    ('╫', [3, 1, 3, 1]),
+    ('╬', [3, 3, 3, 3]),
+    ('╭', [0, 1, 1, 0]),
+    ('╮', [0, 0, 1, 1]),
+    ('╯', [1, 0, 0, 1]),
+    ('╰', [1, 1, 0, 0]),
+    ('╴', [0, 0, 0, 1]),
+    ('╵', [1, 0, 0, 0]),
+    ('╶', [0, 1, 0, 0]),
+    ('╷', [0, 0, 1, 0]),
+    ('╸', [0, 0, 0, 2]),
+    ('╹', [2, 0, 0, 0]),
+    ('╺', [0, 2, 0, 0]),
+    ('╻', [0, 0, 2, 0]),
+    ('╼', [0, 2, 0, 1]),
+    ('╽', [1, 0, 2, 0]),
+    ('╾', [0, 1, 0, 2]),
+    ('╿', [2, 0, 1, 0]),
+];
+
+pub fn arms(c: char) -> Option<Arms> {
+    BOXES.iter().find(|&&(b, _)| b == c).map(|&(_, a)| a)
+}
+
+pub fn compose(a: Arms) -> Option<char> {
+    BOXES.iter().find(|&&(_, b)| b == a).map(|&(c, _)| c)
+}
+
+pub fn is_box(c: char) -> bool {
+    arms(c).is_some()
+}
+
+/// Merges two overlapping box drawing characters into the junction glyph
+/// containing the arms of both, with the heaviest weight winning per arm.
+/// Returns `None` if either isn't a box drawing character or there is no glyph
+/// for the combination.
+pub fn merge(under: char, over: char) -> Option<char> {
+    let (a, b) = (arms(under)?, arms(over)?);
+    compose([
+        a[UP].max(b[UP]),
+        a[RIGHT].max(b[RIGHT]),
+        a[DOWN].max(b[DOWN]),
+        a[LEFT].max(b[LEFT]),
+    ])
+}
+
+/// Mirrors a box drawing character left to right.  Rounded corners only exist
+/// as light lines so they're mapped directly rather than recomposed.
+pub fn mirror_horizontal(c: char) -> Option<char> {
+    match c {
+        '╭' => Some('╮'),
+        '╮' => Some('╭'),
+        '╯' => Some('╰'),
+        '╰' => Some('╯'),
+        _ => arms(c).and_then(|a| compose([a[UP], a[LEFT], a[DOWN], a[RIGHT]])),
+    }
+}
+
+/// Mirrors a box drawing character top to bottom.
+pub fn mirror_vertical(c: char) -> Option<char> {
+    match c {
+        '╭' => Some('╰'),
+        '╮' => Some('╯'),
+        '╯' => Some('╮'),
+        '╰' => Some('╭'),
+        _ => arms(c).and_then(|a| compose([a[DOWN], a[RIGHT], a[UP], a[LEFT]])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_works() {
+        assert_eq!(Some('┬'), merge('┐', '┌'));
+        assert_eq!(Some('┼'), merge('│', '─'));
+        assert_eq!(Some('╋'), merge('┃', '━'));
+        assert_eq!(Some('╪'), merge('│', '═'));
+        assert_eq!(Some('├'), merge('╭', '╰'));
+        assert_eq!(None, merge('a', '─'));
+    }
+
+    #[test]
+    fn mirror_works() {
+        assert_eq!(Some('┐'), mirror_horizontal('┌'));
+        assert_eq!(Some('┥'), mirror_horizontal('┝'));
+        assert_eq!(Some('╮'), mirror_horizontal('╭'));
+        assert_eq!(Some('┴'), mirror_vertical('┬'));
+        assert_eq!(Some('╚'), mirror_vertical('╔'));
+        assert_eq!(Some('─'), mirror_vertical('─'));
+    }
+}
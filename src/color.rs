@@ -0,0 +1,392 @@
+use crate::transform::Player;
+use brdgme_color::{self, player_color, Color, BLACK, PURPLE, WHITE};
+
+use std::cmp;
+
+/// How many entries of the player color palette are tried as alternates.
+const ALTERNATES: usize = 16;
+
+/// A player color which was replaced by `distinct_colors`.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ColorSubstitution {
+    pub player: usize,
+    pub from: Color,
+    pub to: Color,
+}
+
+/// Looks up a color by name, returning `None` for unknown names.  Magenta is
+/// handled manually for compatibility with Go brdgme.
+pub fn lookup_name(name: &str) -> Option<Color> {
+    match name {
+        "magenta" => Some(&PURPLE),
+        _ => brdgme_color::named(name),
+    }.map(|c| c.to_owned())
+}
+
+/// Looks up a color by name, falling back to black for unknown names.
+pub fn from_name(name: &str) -> Color {
+    lookup_name(name).unwrap_or(BLACK)
+}
+
+/// Converts hex digits in either `rgb` or `rrggbb` form to a color, falling
+/// back to black for anything else.
+pub fn from_hex(hex: &str) -> Color {
+    let digits: Vec<u8> = hex.chars()
+        .filter_map(|c| c.to_digit(16))
+        .map(|d| d as u8)
+        .collect();
+    if digits.len() != hex.len() {
+        return BLACK;
+    }
+    match digits.len() {
+        3 => Color {
+            r: digits[0] * 17,
+            g: digits[1] * 17,
+            b: digits[2] * 17,
+        },
+        6 => Color {
+            r: digits[0] * 16 + digits[1],
+            g: digits[2] * 16 + digits[3],
+            b: digits[4] * 16 + digits[5],
+        },
+        _ => BLACK,
+    }
+}
+
+/// Color vision deficiencies which colors can be adjusted for.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ColorBlindness {
+    /// Missing red cones.
+    Protanopia,
+    /// Missing green cones, the most common form.
+    Deuteranopia,
+    /// Missing blue cones.
+    Tritanopia,
+}
+
+fn mul(m: &[[f64; 3]; 3], v: [f64; 3]) -> [f64; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+fn to_color(v: [f64; 3]) -> Color {
+    let channel = |x: f64| x.round().clamp(0.0, 255.0) as u8;
+    Color {
+        r: channel(v[0]),
+        g: channel(v[1]),
+        b: channel(v[2]),
+    }
+}
+
+const RGB_TO_LMS: [[f64; 3]; 3] = [
+    [17.8824, 43.5161, 4.11935],
+    [3.45565, 27.1554, 3.86714],
+    [0.0299566, 0.184309, 1.46709],
+];
+
+const LMS_TO_RGB: [[f64; 3]; 3] = [
+    [0.080_944_447_9, -0.130_504_409, 0.116_721_066],
+    [-0.010_248_533_5, 0.054_019_326_6, -0.113_614_708],
+    [-0.000_365_296_938, -0.004_121_614_69, 0.693_511_405],
+];
+
+/// Approximates how a color appears with the given deficiency.
+pub fn simulate(c: &Color, cb: ColorBlindness) -> Color {
+    let [l, m, s] = mul(&RGB_TO_LMS, [f64::from(c.r), f64::from(c.g), f64::from(c.b)]);
+    let lms = match cb {
+        ColorBlindness::Protanopia => [2.02344 * m - 2.52581 * s, m, s],
+        ColorBlindness::Deuteranopia => [l, 0.494207 * l + 1.24827 * s, s],
+        ColorBlindness::Tritanopia => [l, m, -0.395913 * l + 0.801109 * m],
+    };
+    to_color(mul(&LMS_TO_RGB, lms))
+}
+
+/// Adjusts a color so differences lost to the given deficiency are shifted
+/// into channels which can still be seen, known as daltonization.
+pub fn daltonize(c: &Color, cb: ColorBlindness) -> Color {
+    let sim = simulate(c, cb);
+    let err = [
+        f64::from(c.r) - f64::from(sim.r),
+        f64::from(c.g) - f64::from(sim.g),
+        f64::from(c.b) - f64::from(sim.b),
+    ];
+    let shift = match cb {
+        ColorBlindness::Protanopia | ColorBlindness::Deuteranopia => {
+            [[0.0, 0.0, 0.0], [0.7, 1.0, 0.0], [0.7, 0.0, 1.0]]
+        }
+        ColorBlindness::Tritanopia => [[1.0, 0.0, 0.7], [0.0, 1.0, 0.7], [0.0, 0.0, 0.0]],
+    };
+    let correction = mul(&shift, err);
+    to_color([
+        f64::from(c.r) + correction[0],
+        f64::from(c.g) + correction[1],
+        f64::from(c.b) + correction[2],
+    ])
+}
+
+/// Mixes `percent` percent of `b` into `a`.
+pub fn mix(a: &Color, b: &Color, percent: u8) -> Color {
+    let p = u32::from(cmp::min(percent, 100));
+    let channel = |x: u8, y: u8| ((u32::from(x) * (100 - p) + u32::from(y) * p + 50) / 100) as u8;
+    Color {
+        r: channel(a.r, b.r),
+        g: channel(a.g, b.g),
+        b: channel(a.b, b.b),
+    }
+}
+
+fn linear(c: u8) -> f64 {
+    let c = f64::from(c) / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn lab_f(t: f64) -> f64 {
+    if t > 0.008856 {
+        t.cbrt()
+    } else {
+        7.787 * t + 16.0 / 116.0
+    }
+}
+
+/// Relative luminance as defined by WCAG 2.
+pub fn luminance(c: &Color) -> f64 {
+    0.2126 * linear(c.r) + 0.7152 * linear(c.g) + 0.0722 * linear(c.b)
+}
+
+/// The WCAG contrast ratio between two colors, from 1 to 21.
+pub fn contrast_ratio(a: &Color, b: &Color) -> f64 {
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn min_contrast(c: &Color, bgs: &[Color]) -> f64 {
+    bgs.iter()
+        .map(|bg| contrast_ratio(c, bg))
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Adjusts `fg` so it meets the contrast `ratio` against each of `bgs`,
+/// keeping as much of the original color as possible by mixing towards
+/// whichever of black or white contrasts better.
+pub fn readable(fg: &Color, bgs: &[Color], ratio: f64) -> Color {
+    if min_contrast(fg, bgs) >= ratio {
+        return *fg;
+    }
+    let target = if min_contrast(&BLACK, bgs) >= min_contrast(&WHITE, bgs) {
+        BLACK
+    } else {
+        WHITE
+    };
+    (1..10)
+        .map(|step| mix(fg, &target, step * 10))
+        .find(|c| min_contrast(c, bgs) >= ratio)
+        .unwrap_or(target)
+}
+
+/// Converts an sRGB color to CIE L*a*b* using the D65 white point.
+pub fn lab(c: &Color) -> (f64, f64, f64) {
+    let (r, g, b) = (linear(c.r), linear(c.g), linear(c.b));
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let (fx, fy, fz) = (lab_f(x), lab_f(y), lab_f(z));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+/// The perceptual distance between two colors, as the CIE76 delta E.  A
+/// distance of around 2 is barely noticeable, and colors closer than 20 or so
+/// are easily confused at a glance.
+pub fn distance(a: &Color, b: &Color) -> f64 {
+    lab_distance(&lab(a), &lab(b))
+}
+
+/// The distance between two colors already converted with `lab`, for callers
+/// comparing against the same colors repeatedly.
+pub fn lab_distance(a: &(f64, f64, f64), b: &(f64, f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)).sqrt()
+}
+
+fn min_distance(c: &Color, others: &[Color]) -> f64 {
+    others
+        .iter()
+        .map(|o| distance(c, o))
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Reassigns player colors which are perceptually closer than `threshold` to
+/// an earlier player's color, so players can be told apart in rendered markup.
+/// Earlier players keep their colors, and replacements are taken from the
+/// `player_color` palette, preferring the first one which is far enough from
+/// every other player.  Returns the adjusted players along with the
+/// substitutions made.
+pub fn distinct_colors(
+    players: &[Player],
+    threshold: f64,
+) -> (Vec<Player>, Vec<ColorSubstitution>) {
+    let mut colors: Vec<Color> = players.iter().map(|p| p.color).collect();
+    let mut subs: Vec<ColorSubstitution> = vec![];
+    for p in 0..colors.len() {
+        if min_distance(&colors[p], &colors[..p]) >= threshold {
+            continue;
+        }
+        let others: Vec<Color> = colors
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != p)
+            .map(|(_, c)| *c)
+            .collect();
+        let candidates: Vec<(Color, f64)> = (0..ALTERNATES)
+            .map(|i| {
+                let c = *player_color(i);
+                (c, min_distance(&c, &others))
+            })
+            .collect();
+        let best = candidates
+            .iter()
+            .find(|&&(_, d)| d >= threshold)
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+            });
+        if let Some(&(to, d)) = best {
+            // Only swap if it is actually an improvement.
+            if d > min_distance(&colors[p], &others) {
+                subs.push(ColorSubstitution {
+                    player: p,
+                    from: colors[p],
+                    to,
+                });
+                colors[p] = to;
+            }
+        }
+    }
+    (
+        players
+            .iter()
+            .zip(colors)
+            .map(|(p, color)| Player {
+                name: p.name.to_string(),
+                color,
+            })
+            .collect(),
+        subs,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brdgme_color::*;
+
+    fn players(colors: &[Color]) -> Vec<Player> {
+        colors
+            .iter()
+            .enumerate()
+            .map(|(i, &color)| Player {
+                name: format!("p{}", i),
+                color,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn distance_works() {
+        assert_eq!(0.0, distance(&RED, &RED));
+        assert!((distance(&BLACK, &WHITE) - 100.0).abs() < 0.1);
+        assert!(distance(&RED, &Color { r: 250, g: 60, b: 50 }) < 5.0);
+    }
+
+    #[test]
+    fn named_works() {
+        assert_eq!(RED, from_name("red"));
+        assert_eq!(PURPLE, from_name("magenta"));
+        assert_eq!(BLACK, from_name("chartreuse"));
+        assert_eq!(Some(PURPLE), lookup_name("magenta"));
+        assert_eq!(None, lookup_name("chartreuse"));
+    }
+
+    #[test]
+    fn from_hex_works() {
+        assert_eq!(Color { r: 255, g: 136, b: 0 }, from_hex("f80"));
+        assert_eq!(Color { r: 255, g: 136, b: 1 }, from_hex("FF8801"));
+        assert_eq!(BLACK, from_hex("f8"));
+        assert_eq!(BLACK, from_hex("fgh"));
+    }
+
+    #[test]
+    fn daltonize_works() {
+        for &cb in &[
+            ColorBlindness::Protanopia,
+            ColorBlindness::Deuteranopia,
+            ColorBlindness::Tritanopia,
+        ] {
+            // Greys are seen the same with every deficiency.
+            assert!(distance(&GREY, &daltonize(&GREY, cb)) < 2.0);
+            assert!(distance(&WHITE, &daltonize(&WHITE, cb)) < 2.0);
+        }
+        // Red and green are hard to tell apart without green cones, and
+        // become easier after adjustment.
+        let cb = ColorBlindness::Deuteranopia;
+        let before = distance(&simulate(&RED, cb), &simulate(&GREEN, cb));
+        let after = distance(
+            &simulate(&daltonize(&RED, cb), cb),
+            &simulate(&daltonize(&GREEN, cb), cb),
+        );
+        assert!(after > before);
+    }
+
+    #[test]
+    fn mix_works() {
+        assert_eq!(RED, mix(&RED, &BLUE, 0));
+        assert_eq!(BLUE, mix(&RED, &BLUE, 100));
+        assert_eq!(BLUE, mix(&RED, &BLUE, 200));
+        assert_eq!(Color { r: 128, g: 128, b: 128 }, mix(&BLACK, &WHITE, 50));
+    }
+
+    #[test]
+    fn contrast_ratio_works() {
+        assert!((contrast_ratio(&BLACK, &WHITE) - 21.0).abs() < 0.01);
+        assert!((contrast_ratio(&WHITE, &BLACK) - 21.0).abs() < 0.01);
+        assert_eq!(1.0, contrast_ratio(&RED, &RED));
+    }
+
+    #[test]
+    fn readable_works() {
+        assert_eq!(BLACK, readable(&BLACK, &[WHITE], 4.5));
+        let on_white = readable(&AMBER, &[WHITE], 4.5);
+        assert!(contrast_ratio(&on_white, &WHITE) >= 4.5);
+        assert!(on_white.r > on_white.b);
+        let on_black = readable(&BLUE, &[BLACK], 7.0);
+        assert!(contrast_ratio(&on_black, &BLACK) >= 7.0);
+        assert_eq!(BLACK, readable(&GREY, &[BLACK, WHITE], 21.0));
+    }
+
+    #[test]
+    fn distinct_colors_works() {
+        let close = Color {
+            r: 250,
+            g: 60,
+            b: 50,
+        };
+        let (adjusted, subs) = distinct_colors(&players(&[RED, BLUE, close]), 20.0);
+        assert_eq!(1, subs.len());
+        assert_eq!(2, subs[0].player);
+        assert_eq!(close, subs[0].from);
+        assert_eq!(RED, adjusted[0].color);
+        assert_eq!(BLUE, adjusted[1].color);
+        assert_eq!(subs[0].to, adjusted[2].color);
+        assert!(distance(&adjusted[2].color, &RED) >= 20.0);
+        assert!(distance(&adjusted[2].color, &BLUE) >= 20.0);
+
+        let (_, subs) = distinct_colors(&players(&[RED, BLUE, GREEN]), 20.0);
+        assert!(subs.is_empty());
+    }
+}
@@ -1,4 +1,6 @@
 use crate::ast::TNode;
+use crate::runs::{runs, Attrs};
+use crate::transform::to_lines;
 use brdgme_color::Color;
 
 fn fg(color: &Color, content: &str) -> String {
@@ -24,6 +26,11 @@ fn escape(input: &str) -> String {
         .replace(">", "&gt;")
 }
 
+/// Escapes text so it is also safe inside quoted attribute values.
+fn escape_attr(input: &str) -> String {
+    escape(input).replace("\"", "&quot;").replace("'", "&#39;")
+}
+
 pub fn render(input: &[TNode]) -> String {
     render_nodes(input)
 }
@@ -40,3 +47,219 @@ fn render_nodes(input: &[TNode]) -> String {
     }
     buf
 }
+
+/// The classes for a run of text rendered by `render_classes`.
+fn classes(attrs: &Attrs) -> Vec<String> {
+    let mut classes = vec![];
+    if let Some(ref fg) = attrs.fg {
+        classes.push(format!("brdgme-fg-{}", hex(fg)));
+    }
+    if let Some(ref bg) = attrs.bg {
+        classes.push(format!("brdgme-bg-{}", hex(bg)));
+    }
+    if attrs.bold {
+        classes.push("brdgme-b".to_string());
+    }
+    classes
+}
+
+fn hex(c: &Color) -> String {
+    format!("{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+}
+
+/// Renders using classes instead of inline styles, with a single flat span
+/// for each run of identically styled text.  Use `stylesheet` to generate the
+/// matching CSS.
+pub fn render_classes(input: &[TNode]) -> String {
+    let mut rs = vec![];
+    runs(input, Attrs::default(), &mut rs);
+    rs.into_iter()
+        .map(|(attrs, text)| {
+            let classes = classes(&attrs);
+            if classes.is_empty() {
+                escape(&text)
+            } else {
+                format!(
+                    r#"<span class="{}">{}</span>"#,
+                    classes.join(" "),
+                    escape(&text)
+                )
+            }
+        })
+        .collect()
+}
+
+/// Generates CSS for the classes used by `render_classes` for the given
+/// nodes, with one rule per color used.
+pub fn stylesheet(input: &[TNode]) -> String {
+    let mut rs = vec![];
+    runs(input, Attrs::default(), &mut rs);
+    let mut fgs: Vec<String> = rs.iter().filter_map(|&(a, _)| a.fg.map(|c| hex(&c))).collect();
+    let mut bgs: Vec<String> = rs.iter().filter_map(|&(a, _)| a.bg.map(|c| hex(&c))).collect();
+    fgs.sort();
+    fgs.dedup();
+    bgs.sort();
+    bgs.dedup();
+    let mut rules: Vec<String> = fgs.iter()
+        .map(|h| format!(".brdgme-fg-{}{{color:#{};}}", h, h))
+        .collect();
+    rules.extend(
+        bgs.iter()
+            .map(|h| format!(".brdgme-bg-{}{{background-color:#{};}}", h, h)),
+    );
+    if rs.iter().any(|&(a, _)| a.bold) {
+        rules.push(".brdgme-b{font-weight:bold;}".to_string());
+    }
+    rules.join("\n")
+}
+
+/// Options for rendering a `<pre>` fragment with `render_pre`.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct PreOptions {
+    /// Prefix each line with its number, in a `brdgme-ln` span.
+    pub line_numbers: bool,
+    /// Use classes instead of inline styles, as in `render_classes`.
+    pub classes: bool,
+    /// Extra `data-` attributes added to the `<pre>` element, with names
+    /// given without the `data-` prefix.  Attributes whose names have no valid
+    /// characters are skipped.
+    pub data: Vec<(String, String)>,
+}
+
+/// Limits data attribute names to characters which are always valid.
+fn data_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Renders a self contained `<pre>` fragment, with each line styled
+/// separately so lines never share spans.
+pub fn render_pre(input: &[TNode], opts: &PreOptions) -> String {
+    let lines = to_lines(input);
+    let num_width = lines.len().to_string().len();
+    let body = lines
+        .iter()
+        .enumerate()
+        .map(|(i, l)| {
+            let content = if opts.classes {
+                render_classes(l)
+            } else {
+                render_nodes(l)
+            };
+            if opts.line_numbers {
+                format!(
+                    r#"<span class="brdgme-ln">{:>width$} </span>{}"#,
+                    i + 1,
+                    content,
+                    width = num_width
+                )
+            } else {
+                content
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let attrs: String = opts.data
+        .iter()
+        .filter_map(|&(ref name, ref value)| {
+            // Names with no valid characters would give a bare `data-`.
+            let name = data_name(name);
+            if name.is_empty() {
+                None
+            } else {
+                Some(format!(r#" data-{}="{}""#, name, escape_attr(value)))
+            }
+        })
+        .collect();
+    format!(
+        r#"<pre class="brdgme" style="white-space:pre;"{}>{}</pre>"#,
+        attrs,
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brdgme_color::*;
+
+    #[test]
+    fn escape_works() {
+        assert_eq!(
+            r#"&lt;a href="x"&gt;Tom &amp; Jerry's&lt;/a&gt;"#,
+            escape(r#"<a href="x">Tom & Jerry's</a>"#)
+        );
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;",
+            escape_attr(r#"<a href="x">Tom & Jerry's</a>"#)
+        );
+    }
+
+    #[test]
+    fn render_pre_works() {
+        let input: Vec<TNode> = (1..11)
+            .map(|i| TNode::text(format!("{}\n", i)))
+            .chain(Some(TNode::Bold(vec![TNode::text("a  \"b\"\nc")])))
+            .collect();
+        let opts = PreOptions {
+            line_numbers: true,
+            classes: true,
+            data: vec![
+                ("game-id".to_string(), "1\"2".to_string()),
+                ("on<click".to_string(), "x".to_string()),
+                ("<>".to_string(), "y".to_string()),
+            ],
+        };
+        let out = render_pre(&input, &opts);
+        assert!(out.starts_with(concat!(
+            r#"<pre class="brdgme" style="white-space:pre;" "#,
+            r#"data-game-id="1&quot;2" data-onclick="x"><span"#
+        )));
+        assert!(out.contains("<span class=\"brdgme-ln\"> 1 </span>1\n"));
+        assert!(out.contains(
+            "<span class=\"brdgme-ln\">11 </span><span class=\"brdgme-b\">a  \"b\"</span>\n"
+        ));
+        assert!(out.ends_with(
+            "<span class=\"brdgme-ln\">12 </span><span class=\"brdgme-b\">c</span></pre>"
+        ));
+        assert_eq!(
+            r#"<pre class="brdgme" style="white-space:pre;"><b>a</b></pre>"#,
+            render_pre(&[TNode::Bold(vec![TNode::text("a")])], &PreOptions::default())
+        );
+    }
+
+    #[test]
+    fn render_classes_works() {
+        let red = Color { r: 255, g: 0, b: 0 };
+        let input = vec![
+            TNode::text("a<"),
+            TNode::Fg(
+                red,
+                vec![
+                    TNode::text("b"),
+                    TNode::Bg(WHITE, vec![TNode::Bold(vec![TNode::text("c")])]),
+                ],
+            ),
+            TNode::Fg(red, vec![TNode::text("d")]),
+            TNode::Fg(red, vec![TNode::text("e")]),
+        ];
+        assert_eq!(
+            concat!(
+                r#"a&lt;<span class="brdgme-fg-ff0000">b</span>"#,
+                r#"<span class="brdgme-fg-ff0000 brdgme-bg-ffffff brdgme-b">c</span>"#,
+                r#"<span class="brdgme-fg-ff0000">de</span>"#
+            ),
+            render_classes(&input)
+        );
+        assert_eq!(
+            [
+                ".brdgme-fg-ff0000{color:#ff0000;}",
+                ".brdgme-bg-ffffff{background-color:#ffffff;}",
+                ".brdgme-b{font-weight:bold;}",
+            ].join("\n"),
+            stylesheet(&input)
+        );
+    }
+}
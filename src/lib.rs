@@ -1,28 +1,74 @@
 pub mod ast;
+mod box_drawing;
+mod color;
 mod error;
 mod transform;
 mod ansi;
 mod html;
+mod mirror;
 mod plain;
 mod parser;
+mod runs;
+mod scoreboard;
 
-pub use crate::transform::{from_lines, to_lines, transform, Player};
-pub use crate::ast::{row_pad, row_pad_cell, Align, Node, Row, TNode};
+pub use crate::transform::{from_lines, player_symbol, to_lines, transform, transform_with,
+                           Context, NameFormat, Player, PlayerStyle, Team, Theme, Viewer, You};
+pub use crate::ast::{row_pad, row_pad_cell, Align, Bar, Board, BoardLabels, ChartBar, Flip, Form,
+                     GlyphStyle, Hex, HexOrientation, Node, PlayerOpt, PlayerRef, Row, Suit,
+                     ThemeRole, TNode};
+pub use crate::scoreboard::{Order, Scoreboard, Ties};
+pub use crate::color::{daltonize, distinct_colors, simulate, ColorBlindness, ColorSubstitution};
 use crate::parser::parse;
 pub use crate::error::MarkupError;
+pub use crate::ansi::ColorDepth;
+pub use crate::html::PreOptions;
 
 pub fn html(input: &[TNode]) -> String {
     html::render(input)
 }
 
+pub fn html_classes(input: &[TNode]) -> String {
+    html::render_classes(input)
+}
+
+pub fn html_stylesheet(input: &[TNode]) -> String {
+    html::stylesheet(input)
+}
+
+pub fn html_pre(input: &[TNode], opts: &PreOptions) -> String {
+    html::render_pre(input, opts)
+}
+
 pub fn ansi(input: &[TNode]) -> String {
     ansi::render(input)
 }
 
+pub fn ansi_depth(input: &[TNode], depth: ColorDepth) -> String {
+    ansi::render_depth(input, depth)
+}
+
+pub fn ansi_minimal(input: &[TNode], depth: ColorDepth) -> String {
+    ansi::render_minimal(input, depth)
+}
+
+pub fn ansi_lines(input: &[TNode], depth: ColorDepth) -> Vec<String> {
+    ansi::render_lines(input, depth)
+}
+
+pub fn ansi_paged(input: &[TNode], depth: ColorDepth) -> String {
+    ansi::render_paged(input, depth)
+}
+
 pub fn plain(input: &[TNode]) -> String {
     plain::render(input)
 }
 
+/// Imports previously rendered ANSI output so it can be rendered again, such
+/// as to HTML.
+pub fn from_ansi(input: &str) -> Vec<TNode> {
+    ansi::parse(input)
+}
+
 pub fn from_string(input: &str) -> Result<(Vec<Node>, &str), MarkupError> {
     parse(input)
         .map(|(nodes, remaining)| (nodes, remaining.into_inner()))
@@ -46,28 +92,10 @@ pub fn to_string(input: &[Node]) -> String {
                 to_string(children)
             ),
             Node::Player(p) => format!("{{{{player {}}}}}", p),
+            Node::PlayerRef(ref r) => format!("{{{{player {}}}}}", r.markup_args()),
+            Node::Team(t) => format!("{{{{team {}}}}}", t),
             Node::Group(ref c) => to_string(c),
-            Node::Table(ref rows) => format!(
-                "{{{{table}}}}{}{{{{/table}}}}",
-                rows.iter()
-                    .map(|r| {
-                        format!(
-                            "{{{{row}}}}{}{{{{/row}}}}",
-                            r.iter()
-                                .map(|&(ref align, ref children)| {
-                                    format!(
-                                        "{{{{cell {}}}}}{}{{{{/cell}}}}",
-                                        align.to_string(),
-                                        to_string(children)
-                                    )
-                                })
-                                .collect::<Vec<String>>()
-                                .join("")
-                        )
-                    })
-                    .collect::<Vec<String>>()
-                    .join("")
-            ),
+            Node::Table(ref rows) => format!("{{{{table}}}}{}{{{{/table}}}}", rows_to_string(rows)),
             Node::Align(ref al, width, ref children) => format!(
                 "{{{{align {} {}}}}}{}{{{{/align}}}}",
                 al.to_string(),
@@ -79,21 +107,161 @@ pub fn to_string(input: &[Node]) -> String {
                 width,
                 to_string(children)
             ),
-            Node::Canvas(ref layers) => format!(
-                "{{{{canvas}}}}{}{{{{/canvas}}}}",
-                layers
+            Node::Canvas(ref layers) => {
+                format!("{{{{canvas}}}}{}{{{{/canvas}}}}", layers_to_string(layers))
+            }
+            Node::MergeCanvas(ref layers) => format!(
+                "{{{{canvas merge}}}}{}{{{{/canvas}}}}",
+                layers_to_string(layers)
+            ),
+            Node::Gradient(ref from, ref to, bg, ref children) => format!(
+                "{{{{gradient {} {}{}}}}}{}{{{{/gradient}}}}",
+                from.markup_args(),
+                to.markup_args(),
+                if bg { " bg" } else { "" },
+                to_string(children)
+            ),
+            Node::Flip(ref f, glyphs, ref children) => format!(
+                "{{{{flip {}{}}}}}{}{{{{/flip}}}}",
+                f.to_string(),
+                if glyphs { " glyphs" } else { "" },
+                to_string(children)
+            ),
+            Node::Board(ref b) => format!(
+                "{{{{board {} {} {} {} {}}}}}{}{{{{/board}}}}",
+                b.cell_width,
+                b.cell_height,
+                b.labels.to_string(),
+                b.light.markup_args(),
+                b.dark.markup_args(),
+                rows_to_string(&b.rows)
+            ),
+            Node::HexGrid(ref orientation, ref hexes) => format!(
+                "{{{{hexgrid {}}}}}{}{{{{/hexgrid}}}}",
+                orientation.to_string(),
+                hexes
                     .iter()
-                    .map(|&(x, y, ref children)| {
+                    .map(|h| {
                         format!(
-                            "{{{{layer {} {}}}}}{}{{{{/layer}}}}",
-                            x,
-                            y,
-                            to_string(children)
+                            "{{{{hex {} {}{}{}}}}}{}{{{{/hex}}}}",
+                            h.col,
+                            h.row,
+                            h.fill
+                                .as_ref()
+                                .map(|c| format!(" fill {}", c.markup_args()))
+                                .unwrap_or_default(),
+                            h.border
+                                .as_ref()
+                                .map(|c| format!(" border {}", c.markup_args()))
+                                .unwrap_or_default(),
+                            to_string(&h.content)
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("")
+            ),
+            Node::Card(ref rank, ref suit, ref style) => format!(
+                "{{{{card {} {}{}}}}}",
+                rank,
+                suit.to_string(),
+                glyph_style_arg(style)
+            ),
+            Node::Die(value, ref style) => {
+                format!("{{{{die {}{}}}}}", value, glyph_style_arg(style))
+            }
+            Node::Bar(ref b) => format!(
+                "{{{{bar {} {} {} {} {}{}}}}}",
+                b.value,
+                b.max,
+                b.width,
+                b.fill.markup_args(),
+                b.empty.markup_args(),
+                if b.text { " text" } else { "" }
+            ),
+            Node::Sparkline(ref values) => format!(
+                "{{{{sparkline{}}}}}",
+                values
+                    .iter()
+                    .map(|v| format!(" {}", v))
+                    .collect::<Vec<String>>()
+                    .join("")
+            ),
+            Node::BarChart(width, ref bars) => format!(
+                "{{{{barchart {}}}}}{}{{{{/barchart}}}}",
+                width,
+                bars.iter()
+                    .map(|b| {
+                        format!(
+                            "{{{{chartbar {} {}}}}}{}{{{{/chartbar}}}}",
+                            b.value,
+                            b.color.markup_args(),
+                            to_string(&b.label)
                         )
                     })
                     .collect::<Vec<String>>()
                     .join("")
             ),
+            Node::Only(ref ps, ref children) => format!(
+                "{{{{only{}}}}}{}{{{{/only}}}}",
+                players_args(ps),
+                to_string(children)
+            ),
+            Node::Except(ref ps, ref children) => format!(
+                "{{{{except{}}}}}{}{{{{/except}}}}",
+                players_args(ps),
+                to_string(children)
+            ),
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn players_args(players: &[usize]) -> String {
+    players
+        .iter()
+        .map(|p| format!(" {}", p))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn glyph_style_arg(style: &GlyphStyle) -> String {
+    match *style {
+        GlyphStyle::Compact => "".to_string(),
+        _ => format!(" {}", style.to_string()),
+    }
+}
+
+fn rows_to_string(rows: &[Row]) -> String {
+    rows.iter()
+        .map(|r| {
+            format!(
+                "{{{{row}}}}{}{{{{/row}}}}",
+                r.iter()
+                    .map(|&(ref align, ref children)| {
+                        format!(
+                            "{{{{cell {}}}}}{}{{{{/cell}}}}",
+                            align.to_string(),
+                            to_string(children)
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("")
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn layers_to_string(layers: &[(usize, usize, Vec<Node>)]) -> String {
+    layers
+        .iter()
+        .map(|&(x, y, ref children)| {
+            format!(
+                "{{{{layer {} {}}}}}{}{{{{/layer}}}}",
+                x,
+                y,
+                to_string(children)
+            )
         })
         .collect::<Vec<String>>()
         .join("")
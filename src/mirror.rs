@@ -0,0 +1,65 @@
+use crate::box_drawing;
+
+/// Glyphs which become each other when flipped left to right.
+const HORIZONTAL: &[(char, char)] = &[
+    ('<', '>'),
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('/', '\\'),
+    ('«', '»'),
+    ('⟨', '⟩'),
+    ('←', '→'),
+    ('↖', '↗'),
+    ('↙', '↘'),
+    ('⇐', '⇒'),
+    ('◀', '▶'),
+    ('◁', '▷'),
+    ('◄', '►'),
+    ('◣', '◢'),
+    ('◤', '◥'),
+    ('▌', '▐'),
+];
+
+/// Glyphs which become each other when flipped top to bottom.
+const VERTICAL: &[(char, char)] = &[
+    ('/', '\\'),
+    ('_', '‾'),
+    ('↑', '↓'),
+    ('↖', '↙'),
+    ('↗', '↘'),
+    ('⇑', '⇓'),
+    ('▲', '▼'),
+    ('△', '▽'),
+    ('◤', '◣'),
+    ('◥', '◢'),
+    ('▀', '▄'),
+];
+
+fn swap(pairs: &[(char, char)], c: char) -> Option<char> {
+    pairs.iter().find_map(|&(a, b)| {
+        if c == a {
+            Some(b)
+        } else if c == b {
+            Some(a)
+        } else {
+            None
+        }
+    })
+}
+
+/// Maps a directional glyph to its left to right mirror image, leaving other
+/// characters unchanged.
+pub fn horizontal(c: char) -> char {
+    box_drawing::mirror_horizontal(c)
+        .or_else(|| swap(HORIZONTAL, c))
+        .unwrap_or(c)
+}
+
+/// Maps a directional glyph to its top to bottom mirror image, leaving other
+/// characters unchanged.
+pub fn vertical(c: char) -> char {
+    box_drawing::mirror_vertical(c)
+        .or_else(|| swap(VERTICAL, c))
+        .unwrap_or(c)
+}
@@ -1,13 +1,17 @@
 use combine::{many, Parser, many1};
-use combine::char::{digit, letter, string};
-use combine::combinator::{choice, none_of, parser, r#try};
-use combine::primitives::{ParseResult, Stream};
+use combine::char::{alpha_num, char, digit, hex_digit, letter, string};
+use combine::combinator::{choice, none_of, optional, parser, r#try};
+use combine::primitives::{Error, ParseResult, Stream};
 
 use std::str::FromStr;
 
 use brdgme_color::*;
 
-use crate::ast::{Align, Cell, Col, ColTrans, ColType, Node, Row};
+use crate::color;
+
+use crate::ast::{Align, Bar, Board, BoardLabels, Cell, ChartBar, Col, ColTrans, ColType, Flip,
+                 GlyphStyle, Hex, HexOrientation, Node, PlayerOpt, PlayerRef, Row, Suit,
+                 ThemeRole};
 
 pub fn parse<I>(input: I) -> ParseResult<Vec<Node>, I>
 where
@@ -19,11 +23,24 @@ where
         bg,
         c,
         player,
+        team,
         canvas,
+        merge_canvas,
         table,
         text,
         align,
         indent,
+        flip,
+        board,
+        hex_grid,
+        card,
+        die,
+        bar,
+        sparkline,
+        bar_chart,
+        only,
+        except,
+        gradient,
     ])).parse_stream(input)
 }
 
@@ -54,6 +71,17 @@ where
         .parse_stream(input)
 }
 
+fn parse_i64<I>(input: I) -> ParseResult<i64, I>
+where
+    I: Stream<Item = char>,
+{
+    (optional(char('-')), many1::<String, _>(digit()))
+        .and_then(|(sign, digits)| {
+            format!("{}{}", if sign.is_some() { "-" } else { "" }, digits).parse::<i64>()
+        })
+        .parse_stream(input)
+}
+
 fn fg<I>(input: I) -> ParseResult<Node, I>
 where
     I: Stream<Item = char>,
@@ -87,7 +115,7 @@ where
     I: Stream<Item = char>,
 {
     (
-        choice([col_type_player, col_type_rgb]),
+        parser(col_type),
         many(parser(col_trans)),
     ).map(|(ct, trans)| {
             Col {
@@ -98,6 +126,20 @@ where
         .parse_stream(input)
 }
 
+fn col_type<I>(input: I) -> ParseResult<ColType, I>
+where
+    I: Stream<Item = char>,
+{
+    choice([
+        col_type_player,
+        col_type_team,
+        col_type_rgb,
+        col_type_theme,
+        col_type_hex,
+        col_type_named,
+    ]).parse_stream(input)
+}
+
 fn col_type_player<I>(input: I) -> ParseResult<ColType, I>
 where
     I: Stream<Item = char>,
@@ -107,6 +149,15 @@ where
         .parse_stream(input)
 }
 
+fn col_type_team<I>(input: I) -> ParseResult<ColType, I>
+where
+    I: Stream<Item = char>,
+{
+    (r#try(string("team(")), parser(parse_usize), string(")"))
+        .map(|(_, t, _)| ColType::Team(t))
+        .parse_stream(input)
+}
+
 fn col_type_rgb<I>(input: I) -> ParseResult<ColType, I>
 where
     I: Stream<Item = char>,
@@ -125,21 +176,125 @@ where
         .parse_stream(input)
 }
 
+fn col_type_theme<I>(input: I) -> ParseResult<ColType, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("theme(")),
+        choice([
+            r#try(string("text")),
+            r#try(string("muted")),
+            r#try(string("highlight")),
+            r#try(string("success")),
+            r#try(string("warning")),
+            r#try(string("danger")),
+            r#try(string("info")),
+        ]),
+        string(")"),
+    ).map(|(_, role, _)| ColType::Theme(ThemeRole::from_str(role).unwrap()))
+        .parse_stream(input)
+}
+
+/// Hex colors in either `#rgb` or `#rrggbb` form.
+fn col_type_hex<I>(input: I) -> ParseResult<ColType, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        char('#'),
+        hex_digit(),
+        hex_digit(),
+        hex_digit(),
+        optional((hex_digit(), hex_digit(), hex_digit())),
+    ).map(|(_, a, b, c, rest)| {
+            let mut hex: String = [a, b, c].iter().collect();
+            if let Some((d, e, f)) = rest {
+                hex.extend(&[d, e, f]);
+            }
+            ColType::Hex(hex)
+        })
+        .parse_stream(input)
+}
+
+/// Color names known to `color::lookup_name`, so typos are caught when
+/// parsing instead of silently rendering black.
+fn col_type_named<I>(input: I) -> ParseResult<ColType, I>
+where
+    I: Stream<Item = char>,
+{
+    many1::<String, _>(letter())
+        .and_then(|name| match color::lookup_name(&name) {
+            Some(_) => Ok(ColType::Named(name)),
+            None => Err(Error::Expected("color name".into())),
+        })
+        .parse_stream(input)
+}
+
 fn col_trans<I>(input: I) -> ParseResult<ColTrans, I>
 where
     I: Stream<Item = char>,
 {
-    (r#try(string(" | ")), choice([string("mono"), string("inv")]))
-        .map(|(_, t)| match t {
+    (
+        r#try(string(" | ")),
+        choice([col_trans_simple, col_trans_percent, col_trans_mix]),
+    ).map(|(_, t)| t)
+        .parse_stream(input)
+}
+
+fn col_trans_simple<I>(input: I) -> ParseResult<ColTrans, I>
+where
+    I: Stream<Item = char>,
+{
+    choice([
+        r#try(string("mono")),
+        r#try(string("inv")),
+        r#try(string("contrast")),
+    ]).map(|t| match t {
             "mono" => ColTrans::Mono,
             "inv" => ColTrans::Inv,
+            "contrast" => ColTrans::Contrast,
+            _ => panic!("invalid transform"),
+        })
+        .parse_stream(input)
+}
+
+fn col_trans_percent<I>(input: I) -> ParseResult<ColTrans, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        choice([
+            r#try(string("lighten(")),
+            r#try(string("darken(")),
+            r#try(string("alpha(")),
+        ]),
+        parser(parse_u8),
+        string(")"),
+    ).map(|(t, n, _)| match t {
+            "lighten(" => ColTrans::Lighten(n),
+            "darken(" => ColTrans::Darken(n),
+            "alpha(" => ColTrans::Alpha(n),
             _ => panic!("invalid transform"),
         })
         .parse_stream(input)
 }
 
-/// Backwards compatibility with Go brdgme. Magenta is handled manually as it doesn't exist in this
-/// version of brdgme.
+fn col_trans_mix<I>(input: I) -> ParseResult<ColTrans, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("mix(")),
+        parser(col_type),
+        string(","),
+        parser(parse_u8),
+        string(")"),
+    ).map(|(_, ct, _, n, _)| ColTrans::Mix(ct, n))
+        .parse_stream(input)
+}
+
+/// Backwards compatibility with Go brdgme.
 fn c<I>(input: I) -> ParseResult<Node, I>
 where
     I: Stream<Item = char>,
@@ -150,29 +305,97 @@ where
         string("}}"),
         parser(parse),
         string("{{/c}}"),
-    ).map(|(_, col, _, children, _)| {
-            Node::Fg(
-                match col.as_ref() {
-                    "magenta" => Some(&PURPLE),
-                    _ => named(&col),
-                }.unwrap_or(&BLACK)
-                    .to_owned()
-                    .into(),
-                children,
-            )
-        })
+    ).map(|(_, col, _, children, _)| Node::Fg(color::from_name(&col).into(), children))
         .parse_stream(input)
 }
 
+/// Player references without any arguments are parsed as `Node::Player`.
 fn player<I>(input: I) -> ParseResult<Node, I>
 where
     I: Stream<Item = char>,
 {
-    (r#try(string("{{player ")), parser(parse_usize), string("}}"))
-        .map(|(_, p, _)| Node::Player(p))
+    (
+        r#try(string("{{player ")),
+        parser(parse_usize),
+        many((r#try(string(" ")), parser(player_arg)).map(|(_, arg)| arg)),
+        string("}}"),
+    ).map(|(_, p, args, _): (_, _, Vec<&str>, _)| {
+            if args.is_empty() {
+                return Node::Player(p);
+            }
+            let mut r = PlayerRef::new(p);
+            for arg in args {
+                r = match arg {
+                    "possessive" => r.possessive(),
+                    "cap" => r.cap(),
+                    _ => r.opt(PlayerOpt::from_str(arg).unwrap()),
+                };
+            }
+            Node::PlayerRef(r)
+        })
+        .parse_stream(input)
+}
+
+fn team<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (r#try(string("{{team ")), parser(parse_usize), string("}}"))
+        .map(|(_, t, _)| Node::Team(t))
+        .parse_stream(input)
+}
+
+fn player_arg<I>(input: I) -> ParseResult<&'static str, I>
+where
+    I: Stream<Item = char>,
+{
+    choice([
+        r#try(string("possessive")),
+        r#try(string("cap")),
+        r#try(string("short")),
+        r#try(string("initials")),
+        r#try(string("plain")),
+        r#try(string("icon")),
+    ]).parse_stream(input)
+}
+
+fn only<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{only")),
+        parser(players_args),
+        string("}}"),
+        parser(parse),
+        string("{{/only}}"),
+    ).map(|(_, ps, _, children, _)| Node::Only(ps, children))
+        .parse_stream(input)
+}
+
+fn except<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{except")),
+        parser(players_args),
+        string("}}"),
+        parser(parse),
+        string("{{/except}}"),
+    ).map(|(_, ps, _, children, _)| Node::Except(ps, children))
         .parse_stream(input)
 }
 
+/// A list of player numbers, each preceded by a space.  The list may be
+/// empty, matching what `to_string` writes for `Node::Only(vec![], ..)`.
+fn players_args<I>(input: I) -> ParseResult<Vec<usize>, I>
+where
+    I: Stream<Item = char>,
+{
+    many((string(" "), parser(parse_usize)).map(|(_, p)| p)).parse_stream(input)
+}
+
 fn canvas<I>(input: I) -> ParseResult<Node, I>
 where
     I: Stream<Item = char>,
@@ -185,6 +408,18 @@ where
         .parse_stream(input)
 }
 
+fn merge_canvas<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{canvas merge}}")),
+        many(parser(layer)),
+        string("{{/canvas}}"),
+    ).map(|(_, layers, _)| Node::MergeCanvas(layers))
+        .parse_stream(input)
+}
+
 fn layer<I>(input: I) -> ParseResult<(usize, usize, Vec<Node>), I>
 where
     I: Stream<Item = char>,
@@ -271,6 +506,268 @@ where
         .parse_stream(input)
 }
 
+fn flip<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{flip ")),
+        choice([string("horizontal"), string("vertical"), string("rotate")]),
+        optional(r#try(string(" glyphs"))),
+        string("}}"),
+        parser(parse),
+        string("{{/flip}}"),
+    ).map(|(_, f, glyphs, _, children, _)| {
+            Node::Flip(Flip::from_str(f).unwrap(), glyphs.is_some(), children)
+        })
+        .parse_stream(input)
+}
+
+fn gradient<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{gradient ")),
+        parser(col_args),
+        string(" "),
+        parser(col_args),
+        optional(r#try(string(" bg"))),
+        string("}}"),
+        parser(parse),
+        string("{{/gradient}}"),
+    ).map(|(_, from, _, to, bg, _, children, _)| {
+            Node::Gradient(from, to, bg.is_some(), children)
+        })
+        .parse_stream(input)
+}
+
+fn board<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{board ")),
+        parser(board_args),
+        string("}}"),
+        many(parser(row)),
+        string("{{/board}}"),
+    ).map(|(_, (cell_width, cell_height, labels, light, dark), _, rows, _)| {
+            Node::Board(Board {
+                rows,
+                cell_width,
+                cell_height,
+                light,
+                dark,
+                labels,
+            })
+        })
+        .parse_stream(input)
+}
+
+fn board_args<I>(input: I) -> ParseResult<(usize, usize, BoardLabels, Col, Col), I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        parser(parse_usize),
+        string(" "),
+        parser(parse_usize),
+        string(" "),
+        choice([
+            r#try(string("none")),
+            r#try(string("chess")),
+            r#try(string("numeric")),
+        ]),
+        string(" "),
+        parser(col_args),
+        string(" "),
+        parser(col_args),
+    ).map(|(cell_width, _, cell_height, _, labels, _, light, _, dark)| {
+            (
+                cell_width,
+                cell_height,
+                BoardLabels::from_str(labels).unwrap(),
+                light,
+                dark,
+            )
+        })
+        .parse_stream(input)
+}
+
+fn hex_grid<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{hexgrid ")),
+        choice([string("pointy"), string("flat")]),
+        string("}}"),
+        many(parser(hex)),
+        string("{{/hexgrid}}"),
+    ).map(|(_, orientation, _, hexes, _)| {
+            Node::HexGrid(HexOrientation::from_str(orientation).unwrap(), hexes)
+        })
+        .parse_stream(input)
+}
+
+fn hex<I>(input: I) -> ParseResult<Hex, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{hex ")),
+        parser(parse_usize),
+        string(" "),
+        parser(parse_usize),
+        optional((r#try(string(" fill ")), parser(col_args))),
+        optional((r#try(string(" border ")), parser(col_args))),
+        string("}}"),
+        parser(parse),
+        string("{{/hex}}"),
+    ).map(|(_, col, _, row, fill, border, _, content, _)| {
+            Hex {
+                col,
+                row,
+                fill: fill.map(|(_, c)| c),
+                border: border.map(|(_, c)| c),
+                content,
+            }
+        })
+        .parse_stream(input)
+}
+
+fn card<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{card ")),
+        many1::<String, _>(alpha_num()),
+        string(" "),
+        choice([
+            string("hearts"),
+            string("diamonds"),
+            string("clubs"),
+            string("spades"),
+        ]),
+        parser(glyph_style_arg),
+        string("}}"),
+    ).map(|(_, rank, _, suit, style, _)| {
+            Node::Card(rank, Suit::from_str(suit).unwrap(), style)
+        })
+        .parse_stream(input)
+}
+
+fn die<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{die ")),
+        parser(parse_usize),
+        parser(glyph_style_arg),
+        string("}}"),
+    ).map(|(_, value, style, _)| Node::Die(value, style))
+        .parse_stream(input)
+}
+
+/// Bars are written as `{{bar value max width}}`, optionally followed by the
+/// fill and empty colors and a `text` flag.
+fn bar<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{bar ")),
+        parser(parse_usize),
+        string(" "),
+        parser(parse_usize),
+        string(" "),
+        parser(parse_usize),
+        optional(r#try((
+            string(" "),
+            parser(col_args),
+            string(" "),
+            parser(col_args),
+        ))),
+        optional(r#try(string(" text"))),
+        string("}}"),
+    ).map(|(_, value, _, max, _, width, cols, text, _)| {
+            let mut b = Bar::new(value, max, width);
+            if let Some((_, fill, _, empty)) = cols {
+                b.fill = fill;
+                b.empty = empty;
+            }
+            b.text = text.is_some();
+            Node::Bar(b)
+        })
+        .parse_stream(input)
+}
+
+fn sparkline<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{sparkline")),
+        many((string(" "), parser(parse_i64)).map(|(_, v)| v)),
+        string("}}"),
+    ).map(|(_, values, _)| Node::Sparkline(values))
+        .parse_stream(input)
+}
+
+fn bar_chart<I>(input: I) -> ParseResult<Node, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{barchart ")),
+        parser(parse_usize),
+        string("}}"),
+        many(parser(chart_bar)),
+        string("{{/barchart}}"),
+    ).map(|(_, width, _, bars, _)| Node::BarChart(width, bars))
+        .parse_stream(input)
+}
+
+fn chart_bar<I>(input: I) -> ParseResult<ChartBar, I>
+where
+    I: Stream<Item = char>,
+{
+    (
+        r#try(string("{{chartbar ")),
+        parser(parse_i64),
+        string(" "),
+        parser(col_args),
+        string("}}"),
+        parser(parse),
+        string("{{/chartbar}}"),
+    ).map(|(_, value, _, color, _, label, _)| {
+            ChartBar {
+                label,
+                value,
+                color,
+            }
+        })
+        .parse_stream(input)
+}
+
+/// An optional trailing glyph style, defaulting to compact.
+fn glyph_style_arg<I>(input: I) -> ParseResult<GlyphStyle, I>
+where
+    I: Stream<Item = char>,
+{
+    optional(r#try((
+        string(" "),
+        choice([string("compact"), string("large"), string("text")]),
+    ))).map(|style| match style {
+            Some((_, s)) => GlyphStyle::from_str(s).unwrap(),
+            None => GlyphStyle::Compact,
+        })
+        .parse_stream(input)
+}
+
 fn align_arg<I>(input: I) -> ParseResult<Align, I>
 where
     I: Stream<Item = char>,
@@ -354,4 +851,270 @@ mod tests {
             parser(parse).parse(to_string(&expected).as_ref())
         );
     }
+
+    #[test]
+    fn merge_canvas_works() {
+        let expected = vec![
+            N::MergeCanvas(vec![
+                (0, 0, vec![N::text("┌─")]),
+                (1, 0, vec![N::Bold(vec![N::text("┬┐")])]),
+            ]),
+        ];
+        assert_eq!(
+            "{{canvas merge}}{{layer 0 0}}┌─{{/layer}}{{layer 1 0}}{{b}}┬┐{{/b}}{{/layer}}\
+             {{/canvas}}",
+            to_string(&expected)
+        );
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn flip_works() {
+        let expected = vec![
+            N::Flip(Flip::Horizontal, true, vec![N::text("┌─>")]),
+            N::Flip(Flip::Vertical, false, vec![N::Fg(RED.into(), vec![N::text("a")])]),
+            N::Flip(Flip::Rotate, false, vec![]),
+        ];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn board_works() {
+        let rows = vec![
+            vec![(A::Center, vec![N::text("♜")]), (A::Center, vec![])],
+            vec![(A::Left, vec![]), (A::Center, vec![N::Bold(vec![N::text("♙")])])],
+        ];
+        for labels in &[BoardLabels::None, BoardLabels::Chess, BoardLabels::Numeric] {
+            let expected = vec![
+                N::Board(Board {
+                    rows: rows.clone(),
+                    cell_width: 3,
+                    cell_height: 1,
+                    light: WHITE.into(),
+                    dark: GREY.into(),
+                    labels: labels.clone(),
+                }),
+            ];
+            assert_eq!(
+                Ok((expected.clone(), "")),
+                parser(parse).parse(to_string(&expected).as_ref())
+            );
+        }
+    }
+
+    #[test]
+    fn hex_grid_works() {
+        let expected = vec![
+            N::HexGrid(
+                HexOrientation::Flat,
+                vec![
+                    Hex {
+                        col: 0,
+                        row: 0,
+                        fill: None,
+                        border: None,
+                        content: vec![N::text("a")],
+                    },
+                    Hex {
+                        col: 1,
+                        row: 0,
+                        fill: Some(Col::from(ColType::Player(1)).lighten(30)),
+                        border: Some(RED.into()),
+                        content: vec![N::Bold(vec![N::text("b")])],
+                    },
+                    Hex {
+                        col: 2,
+                        row: 1,
+                        fill: None,
+                        border: Some(Col::from(ColType::Theme(ThemeRole::Muted))),
+                        content: vec![],
+                    },
+                ],
+            ),
+            N::HexGrid(HexOrientation::Pointy, vec![]),
+        ];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn card_and_die_works() {
+        assert_eq!(
+            Ok((
+                vec![N::Card("Q".to_string(), Suit::Hearts, GlyphStyle::Compact)],
+                "",
+            )),
+            parser(parse).parse("{{card Q hearts}}")
+        );
+        assert_eq!(
+            Ok((vec![N::Die(5, GlyphStyle::Compact)], "")),
+            parser(parse).parse("{{die 5}}")
+        );
+        assert_eq!(
+            Ok((vec![N::Die(5, GlyphStyle::Large)], "")),
+            parser(parse).parse("{{die 5 large}}")
+        );
+        let expected = vec![
+            N::Card("10".to_string(), Suit::Spades, GlyphStyle::Large),
+            N::Card("A".to_string(), Suit::Diamonds, GlyphStyle::Text),
+            N::Card("2".to_string(), Suit::Clubs, GlyphStyle::Compact),
+            N::Die(1, GlyphStyle::Text),
+            N::Die(20, GlyphStyle::Compact),
+        ];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn bar_works() {
+        assert_eq!(
+            Ok((vec![N::Bar(Bar::new(9, 16, 5))], "")),
+            parser(parse).parse("{{bar 9 16 5}}")
+        );
+        let mut b = Bar::new(9, 16, 5);
+        b.fill = Col::from(ColType::Player(0));
+        b.empty = Col::from(ColType::Theme(ThemeRole::Muted)).lighten(10);
+        assert_eq!(
+            Ok((vec![N::Bar(b.clone())], "")),
+            parser(parse).parse("{{bar 9 16 5 player(0) theme(muted) | lighten(10)}}")
+        );
+        let mut text = Bar::new(9, 16, 5);
+        text.text = true;
+        assert_eq!(
+            Ok((vec![N::Bar(text)], "")),
+            parser(parse).parse("{{bar 9 16 5 text}}")
+        );
+        b.text = true;
+        let expected = vec![N::Bar(b)];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn only_except_works() {
+        assert_eq!(
+            Ok((vec![N::Only(vec![0, 2], vec![N::text("secret")])], "")),
+            parser(parse).parse("{{only 0 2}}secret{{/only}}")
+        );
+        assert_eq!(
+            Ok((vec![N::Except(vec![1], vec![])], "")),
+            parser(parse).parse("{{except 1}}{{/except}}")
+        );
+        let expected = vec![
+            N::Only(vec![3], vec![N::Bold(vec![N::text("a")])]),
+            N::Except(vec![0, 1, 2], vec![N::Player(1)]),
+            N::Only(vec![], vec![N::text("nobody")]),
+            N::Except(vec![], vec![N::text("everybody")]),
+        ];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn player_works() {
+        assert_eq!(
+            Ok((vec![N::PlayerRef(PlayerRef::new(2).opt(PlayerOpt::Short))], "")),
+            parser(parse).parse("{{player 2 short}}")
+        );
+        assert_eq!(
+            Ok((vec![N::Player(2)], "")),
+            parser(parse).parse("{{player 2}}")
+        );
+        let expected = vec![
+            N::PlayerRef(
+                PlayerRef::new(1)
+                    .possessive()
+                    .cap()
+                    .opt(PlayerOpt::Initials)
+                    .opt(PlayerOpt::Plain)
+                    .opt(PlayerOpt::Icon),
+            ),
+        ];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn team_works() {
+        assert_eq!(Ok((vec![N::Team(1)], "")), parser(parse).parse("{{team 1}}"));
+        assert_eq!(
+            Ok((vec![N::Fg(ColType::Team(1).into(), vec![N::text("a")])], "")),
+            parser(parse).parse("{{fg team(1)}}a{{/fg}}")
+        );
+        let expected = vec![N::Team(0), N::Bg(ColType::Team(2).into(), vec![N::Team(2)])];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn gradient_works() {
+        let expected = vec![
+            N::Gradient(
+                Col::from(ColType::Named("red".to_string())),
+                Col::from(ColType::Player(1)).lighten(20),
+                false,
+                vec![N::text("rainbow")],
+            ),
+            N::Gradient(
+                Col::from(ColType::Theme(ThemeRole::Muted)),
+                Col::from(ColType::Hex("f80".to_string())),
+                true,
+                vec![N::Bold(vec![N::text("a\nb")])],
+            ),
+        ];
+        assert_eq!(
+            Ok((expected.clone(), "")),
+            parser(parse).parse(to_string(&expected).as_ref())
+        );
+    }
+
+    #[test]
+    fn col_args_works() {
+        let col = Col::from(ColType::Team(1))
+            .mix(ColType::Theme(ThemeRole::Muted), 30)
+            .lighten(20)
+            .darken(5)
+            .mix(ColType::RGB(Color { r: 1, g: 2, b: 3 }), 50)
+            .alpha(70)
+            .inv()
+            .contrast();
+        assert_eq!(
+            Ok((Col::from(ColType::Named("red".to_string())), "")),
+            parser(col_args).parse("red")
+        );
+        assert!(parser(col_args).parse("bluee").is_err());
+        assert!(parser(parse).parse("{{fg bluee}}a{{/fg}}").is_err());
+        for hex in &["#f80", "#FF8801"] {
+            let col = Col::from(ColType::Hex(hex[1..].to_string()));
+            assert_eq!(Ok((col.clone(), "")), parser(col_args).parse(*hex));
+            assert_eq!(*hex, col.markup_args());
+        }
+        assert_eq!(
+            "team(1) | mix(theme(muted),30) | lighten(20) | darken(5) | mix(rgb(1,2,3),50) | \
+             alpha(70) | inv | contrast",
+            col.markup_args()
+        );
+        assert_eq!(
+            Ok((col.clone(), "")),
+            parser(col_args).parse(col.markup_args().as_ref())
+        );
+    }
 }
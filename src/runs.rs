@@ -0,0 +1,98 @@
+use crate::ast::TNode;
+use brdgme_color::{Color, Style};
+
+/// The attributes a run of text is rendered with, where `None` colors are left
+/// to the renderer's defaults.
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+pub struct Attrs {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+}
+
+impl<'a> From<Style<'a>> for Attrs {
+    fn from(s: Style<'a>) -> Attrs {
+        Attrs {
+            fg: Some(*s.fg),
+            bg: Some(*s.bg),
+            bold: s.bold,
+        }
+    }
+}
+
+/// Flattens nodes into runs of text with the attributes they are rendered
+/// with, merging neighbouring runs with identical attributes.  Used by
+/// renderers which output flat spans of styled text instead of nesting.
+pub fn runs(input: &[TNode], attrs: Attrs, out: &mut Vec<(Attrs, String)>) {
+    for n in input {
+        match *n {
+            TNode::Text(ref t) => {
+                if t.is_empty() {
+                    continue;
+                }
+                match out.last_mut() {
+                    Some(&mut (last, ref mut text)) if last == attrs => text.push_str(t),
+                    _ => out.push((attrs, t.to_string())),
+                }
+            }
+            TNode::Fg(color, ref children) => runs(
+                children,
+                Attrs {
+                    fg: Some(color),
+                    ..attrs
+                },
+                out,
+            ),
+            TNode::Bg(color, ref children) => runs(
+                children,
+                Attrs {
+                    bg: Some(color),
+                    ..attrs
+                },
+                out,
+            ),
+            TNode::Bold(ref children) => runs(
+                children,
+                Attrs {
+                    bold: true,
+                    ..attrs
+                },
+                out,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brdgme_color::*;
+
+    #[test]
+    fn runs_works() {
+        let mut rs = vec![];
+        runs(
+            &[
+                TNode::text("a"),
+                TNode::Fg(RED, vec![TNode::text("b"), TNode::text("")]),
+                TNode::Fg(RED, vec![TNode::Bold(vec![TNode::text("c")])]),
+                TNode::Bold(vec![TNode::Fg(RED, vec![TNode::text("d")])]),
+            ],
+            Attrs::default(),
+            &mut rs,
+        );
+        let red = Attrs {
+            fg: Some(RED),
+            ..Attrs::default()
+        };
+        let red_bold = Attrs { bold: true, ..red };
+        assert_eq!(
+            vec![
+                (Attrs::default(), "a".to_string()),
+                (red, "b".to_string()),
+                (red_bold, "cd".to_string()),
+            ],
+            rs
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::ast::{row_pad, Align, Node, Row};
+
+use std::cmp::Ordering;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Order {
+    /// Highest values rank first.
+    Desc,
+    /// Lowest values rank first.
+    Asc,
+}
+
+/// How ranks are assigned to players with identical scores.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Ties {
+    /// Tied players share a rank and the following ranks are skipped, eg. 1, 1,
+    /// 3.
+    Shared,
+    /// Tied players share a rank and no ranks are skipped, eg. 1, 1, 2.
+    Dense,
+    /// Ties are broken by player order, eg. 1, 2, 3.
+    Ordered,
+}
+
+/// Builds a ranked score table from named numeric columns.  Rows are sorted
+/// by each sort column in turn, falling back to the first column descending if
+/// none are given, and players ranked first are highlighted as winners.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Scoreboard {
+    pub columns: Vec<String>,
+    pub rows: Vec<(usize, Vec<i64>)>,
+    pub sort: Vec<(usize, Order)>,
+    pub ties: Ties,
+}
+
+impl Scoreboard {
+    pub fn new(columns: &[&str]) -> Scoreboard {
+        Scoreboard {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: vec![],
+            sort: vec![],
+            ties: Ties::Shared,
+        }
+    }
+
+    pub fn player(mut self, player: usize, values: Vec<i64>) -> Self {
+        self.rows.push((player, values));
+        self
+    }
+
+    pub fn sort_by(mut self, column: usize, order: Order) -> Self {
+        self.sort.push((column, order));
+        self
+    }
+
+    pub fn ties(mut self, ties: Ties) -> Self {
+        self.ties = ties;
+        self
+    }
+
+    fn compare(&self, a: &[i64], b: &[i64]) -> Ordering {
+        let default_sort = [(0, Order::Desc)];
+        let sort: &[(usize, Order)] = if self.sort.is_empty() {
+            &default_sort
+        } else {
+            &self.sort
+        };
+        for &(col, order) in sort {
+            let (a_val, b_val) = (a.get(col), b.get(col));
+            let ord = match order {
+                Order::Desc => b_val.cmp(&a_val),
+                Order::Asc => a_val.cmp(&b_val),
+            };
+            if ord != Ordering::Equal {
+                return ord;
+            }
+        }
+        Ordering::Equal
+    }
+
+    /// Rows in rank order, paired with their rank starting from 1.
+    pub fn ranked(&self) -> Vec<(usize, usize, Vec<i64>)> {
+        let mut sorted = self.rows.clone();
+        // Sorting is stable, so ties stay in player order.
+        sorted.sort_by(|a, b| self.compare(&a.1, &b.1));
+        let mut ranked: Vec<(usize, usize, Vec<i64>)> = vec![];
+        for (i, (player, values)) in sorted.into_iter().enumerate() {
+            let rank = match ranked.last() {
+                Some(&(last_rank, _, ref last_values))
+                    if self.ties != Ties::Ordered
+                        && self.compare(last_values, &values) == Ordering::Equal =>
+                {
+                    last_rank
+                }
+                Some(&(last_rank, _, _)) if self.ties == Ties::Dense => last_rank + 1,
+                _ => i + 1,
+            };
+            ranked.push((rank, player, values));
+        }
+        ranked
+    }
+
+    pub fn to_nodes(&self) -> Vec<Node> {
+        let mut header: Row = vec![(Align::Left, vec![]), (Align::Left, vec![])];
+        header.extend(self.columns.iter().map(|c| {
+            (Align::Right, vec![Node::Bold(vec![Node::text(c.to_owned())])])
+        }));
+        let mut rows: Vec<Row> = vec![row_pad(&header, "  ")];
+        for (rank, player, values) in self.ranked() {
+            let mut row: Row = vec![
+                (Align::Right, vec![Node::text(ordinal(rank))]),
+                (Align::Left, vec![Node::Player(player)]),
+            ];
+            row.extend(
+                values
+                    .iter()
+                    .map(|v| (Align::Right, vec![Node::text(v.to_string())])),
+            );
+            if rank == 1 {
+                row = row.into_iter()
+                    .map(|(al, children)| (al, vec![Node::Bold(children)]))
+                    .collect();
+            }
+            rows.push(row_pad(&row, "  "));
+        }
+        vec![Node::Table(rows)]
+    }
+}
+
+fn ordinal(n: usize) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", n, suffix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plain::render;
+    use crate::transform::{transform, Player};
+    use brdgme_color::*;
+
+    fn players() -> Vec<Player> {
+        vec!["Ann", "Bob", "Cat", "Dan"]
+            .into_iter()
+            .map(|name| Player {
+                name: name.to_string(),
+                color: GREEN,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranked_works() {
+        let sb = Scoreboard::new(&["Points", "Coins"])
+            .player(0, vec![10, 2])
+            .player(1, vec![12, 0])
+            .player(2, vec![10, 2])
+            .player(3, vec![8, 5]);
+        let ranks = |sb: &Scoreboard| {
+            sb.ranked()
+                .iter()
+                .map(|&(rank, player, _)| (rank, player))
+                .collect::<Vec<(usize, usize)>>()
+        };
+        assert_eq!(vec![(1, 1), (2, 0), (2, 2), (4, 3)], ranks(&sb));
+        assert_eq!(
+            vec![(1, 1), (2, 0), (2, 2), (3, 3)],
+            ranks(&sb.clone().ties(Ties::Dense))
+        );
+        assert_eq!(
+            vec![(1, 3), (2, 0), (2, 2), (4, 1)],
+            ranks(&sb.clone().sort_by(1, Order::Desc).sort_by(0, Order::Desc))
+        );
+        assert_eq!(
+            vec![(1, 1), (2, 0), (3, 2), (4, 3)],
+            ranks(&sb.clone().sort_by(1, Order::Asc).ties(Ties::Ordered))
+        );
+    }
+
+    #[test]
+    fn to_nodes_works() {
+        let sb = Scoreboard::new(&["Points"])
+            .player(0, vec![5])
+            .player(1, vec![12]);
+        assert_eq!(
+            ["            Points", "1st  <Bob>      12", "2nd  <Ann>       5"].join("\n"),
+            render(&transform(&sb.to_nodes(), &players()))
+        );
+    }
+}
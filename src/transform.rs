@@ -1,5 +1,12 @@
-use crate::ast::{Align, BgRange, Col, ColTrans, ColType, Node, Row, TNode};
-use brdgme_color::{player_color, Color};
+use crate::ast::{row_pad, Align, Bar, BgRange, Board, BoardLabels, ChartBar, Col, ColTrans,
+                 ColType, Flip, Form, GlyphStyle, Hex, HexOrientation, Node, PlayerOpt, PlayerRef,
+                 Row, Suit, ThemeRole, TNode};
+use crate::box_drawing;
+use crate::color::{self, ColorBlindness};
+use crate::mirror;
+use crate::plain;
+use brdgme_color::{player_color, Color, AMBER, BLACK, BLUE, GREEN, GREY, ORANGE, PURPLE, RED,
+                   WHITE};
 
 use std::iter;
 use std::cmp;
@@ -10,66 +17,484 @@ pub struct Player {
     pub color: Color,
 }
 
-impl Col {
-    fn to_color(&self, players: &[Player]) -> Color {
-        let mut c = match self.color {
-            ColType::Player(p) => players
+/// A group of players referred to by `{{team N}}` and `team(N)` colors.
+pub struct Team {
+    pub name: String,
+    pub color: Color,
+    /// Indices into the player list.
+    pub members: Vec<usize>,
+}
+
+impl ColType {
+    fn to_color(&self, ctx: &Context) -> Color {
+        match *self {
+            ColType::Player(p) => ctx.players
                 .get(p)
                 .map(|p| p.color)
                 .unwrap_or_else(|| player_color(p).to_owned()),
+            ColType::Team(t) => ctx.teams
+                .get(t)
+                .map(|t| t.color)
+                .unwrap_or_else(|| player_color(t).to_owned()),
             ColType::RGB(c) => c,
-        };
+            ColType::Named(ref n) => color::from_name(n),
+            ColType::Hex(ref h) => color::from_hex(h),
+            ColType::Theme(r) => ctx.theme.color(r),
+        }
+    }
+}
+
+impl Col {
+    fn to_color(&self, ctx: &Context) -> Color {
+        self.to_color_over(&[ctx.background()], ctx)
+    }
+
+    /// Resolves the color for use over the given backgrounds, which are only
+    /// used by `ColTrans::Contrast`.
+    fn to_color_over(&self, bgs: &[Color], ctx: &Context) -> Color {
+        let mut c = self.color.to_color(ctx);
         for tf in &self.transform {
             c = match *tf {
                 ColTrans::Mono => c.mono(),
                 ColTrans::Inv => c.inv(),
+                ColTrans::Lighten(n) => color::mix(&c, &WHITE, n),
+                ColTrans::Darken(n) => color::mix(&c, &BLACK, n),
+                ColTrans::Mix(ref other, n) => color::mix(&c, &other.to_color(ctx), n),
+                ColTrans::Alpha(n) => color::mix(&ctx.background(), &c, n),
+                ColTrans::Contrast => color::readable(&c, bgs, ctx.contrast),
             }
         }
         c
     }
 }
 
+/// Who transformed output is being rendered for.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Viewer {
+    Player(usize),
+    Spectator,
+}
+
+/// Words used to refer to the viewing player.
+#[derive(PartialEq, Debug, Clone)]
+pub struct You {
+    pub subject: String,
+    pub possessive: String,
+}
+
+impl Default for You {
+    fn default() -> Self {
+        You {
+            subject: "you".to_string(),
+            possessive: "your".to_string(),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum NameFormat {
+    Full,
+    /// Names are cut down to the given number of characters.
+    Short(usize),
+    Initials,
+}
+
+/// Controls how player references are rendered.
+#[derive(PartialEq, Debug, Clone)]
+pub struct PlayerStyle {
+    /// Wrap names in angle brackets, such as `<Mick>`.
+    pub brackets: bool,
+    pub bold: bool,
+    /// Prefix names with the player's symbol from `player_symbol`.
+    pub icon: bool,
+    pub name: NameFormat,
+    /// Label used for players who aren't in the player list, with `{}`
+    /// replaced by the player number.
+    pub fallback: String,
+}
+
+impl Default for PlayerStyle {
+    fn default() -> Self {
+        PlayerStyle {
+            brackets: true,
+            bold: true,
+            icon: false,
+            name: NameFormat::Full,
+            fallback: "Player {}".to_string(),
+        }
+    }
+}
+
+impl PlayerStyle {
+    /// Applies per reference options on top of this style.
+    fn with_opts(&self, opts: &[PlayerOpt]) -> PlayerStyle {
+        let mut style = self.clone();
+        for opt in opts {
+            match *opt {
+                // Keep a configured length, only defaulting to 3.
+                PlayerOpt::Short => {
+                    style.name = match style.name {
+                        NameFormat::Short(len) => NameFormat::Short(len),
+                        _ => NameFormat::Short(3),
+                    }
+                }
+                PlayerOpt::Initials => style.name = NameFormat::Initials,
+                PlayerOpt::Plain => {
+                    style.brackets = false;
+                    style.bold = false;
+                }
+                PlayerOpt::Icon => style.icon = true,
+            }
+        }
+        style
+    }
+
+    fn format_name(&self, name: &str) -> String {
+        match self.name {
+            NameFormat::Full => name.to_string(),
+            NameFormat::Short(len) => name.chars().take(len).collect(),
+            NameFormat::Initials => name.split_whitespace()
+                .filter_map(|word| word.chars().next())
+                .flat_map(|c| c.to_uppercase())
+                .collect(),
+        }
+    }
+}
+
+/// Symbols which can be shown next to player names, so players can be told
+/// apart without relying on color.
+const PLAYER_SYMBOLS: [char; 8] = ['●', '■', '▲', '◆', '★', '✚', '♥', '♣'];
+
+pub fn player_symbol(p: usize) -> char {
+    PLAYER_SYMBOLS[p % PLAYER_SYMBOLS.len()]
+}
+
+/// Colors used for `ColType::Theme` roles, so games can use semantic colors
+/// which suit the background they are displayed on.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Theme {
+    pub background: Color,
+    pub text: Color,
+    pub muted: Color,
+    pub highlight: Color,
+    pub success: Color,
+    pub warning: Color,
+    pub danger: Color,
+    pub info: Color,
+}
+
+impl Theme {
+    pub fn light() -> Theme {
+        Theme {
+            background: WHITE,
+            text: BLACK,
+            muted: GREY,
+            highlight: PURPLE,
+            success: GREEN,
+            warning: ORANGE,
+            danger: RED,
+            info: BLUE,
+        }
+    }
+
+    pub fn dark() -> Theme {
+        Theme {
+            background: Color {
+                r: 33,
+                g: 33,
+                b: 33,
+            },
+            text: WHITE,
+            muted: GREY,
+            highlight: AMBER,
+            success: color::mix(&GREEN, &WHITE, 25),
+            warning: ORANGE,
+            danger: color::mix(&RED, &WHITE, 25),
+            info: color::mix(&BLUE, &WHITE, 25),
+        }
+    }
+
+    pub fn color(&self, role: ThemeRole) -> Color {
+        match role {
+            ThemeRole::Text => self.text,
+            ThemeRole::Muted => self.muted,
+            ThemeRole::Highlight => self.highlight,
+            ThemeRole::Success => self.success,
+            ThemeRole::Warning => self.warning,
+            ThemeRole::Danger => self.danger,
+            ThemeRole::Info => self.info,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+/// Options used when transforming markup.
+#[derive(Clone)]
+pub struct Context<'a> {
+    pub players: &'a [Player],
+    pub teams: &'a [Team],
+    /// If `None`, output is treated as public and content is shown as it would
+    /// be to a spectator.
+    pub viewer: Option<Viewer>,
+    /// If set, references to the viewing player are rendered using these
+    /// words instead of their name.
+    pub you: Option<You>,
+    pub player_style: PlayerStyle,
+    /// The minimum WCAG contrast ratio used by `ColTrans::Contrast`,
+    /// defaulting to the AA level of 4.5.
+    pub contrast: f64,
+    pub theme: Theme,
+    /// If set, all colors are adjusted so they are easier to tell apart with
+    /// the given color vision deficiency.  Setting `PlayerStyle::icon` as
+    /// well adds symbols to player names so they don't rely on color at all.
+    pub color_blindness: Option<ColorBlindness>,
+    /// The background inherited from enclosing `Bg` nodes.
+    bg: Option<Color>,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(players: &'a [Player]) -> Context<'a> {
+        Context {
+            players,
+            teams: &[],
+            viewer: None,
+            you: None,
+            player_style: PlayerStyle::default(),
+            contrast: 4.5,
+            theme: Theme::default(),
+            color_blindness: None,
+            bg: None,
+        }
+    }
+
+    pub fn teams(mut self, teams: &'a [Team]) -> Self {
+        self.teams = teams;
+        self
+    }
+
+    /// The team the given player is a member of, if any.
+    pub fn team_of(&self, player: usize) -> Option<usize> {
+        self.teams.iter().position(|t| t.members.contains(&player))
+    }
+
+    pub fn viewer(mut self, viewer: Viewer) -> Self {
+        self.viewer = Some(viewer);
+        self
+    }
+
+    pub fn you(mut self, you: You) -> Self {
+        self.you = Some(you);
+        self
+    }
+
+    pub fn player_style(mut self, player_style: PlayerStyle) -> Self {
+        self.player_style = player_style;
+        self
+    }
+
+    pub fn contrast(mut self, ratio: f64) -> Self {
+        self.contrast = ratio;
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn color_blindness(mut self, color_blindness: ColorBlindness) -> Self {
+        self.color_blindness = Some(color_blindness);
+        self
+    }
+
+    /// The background color content is currently being rendered on, which is
+    /// the theme background unless inside a `Bg` node.
+    fn background(&self) -> Color {
+        self.bg.unwrap_or(self.theme.background)
+    }
+
+    fn with_bg(&self, bg: Color) -> Context<'a> {
+        Context {
+            bg: Some(bg),
+            ..self.clone()
+        }
+    }
+
+    /// Whether the viewer can see content visible to the given players, or
+    /// hidden from them if `except` is set.
+    fn can_see(&self, players: &[usize], except: bool) -> bool {
+        match self.viewer {
+            None | Some(Viewer::Spectator) => except,
+            Some(Viewer::Player(p)) => players.contains(&p) != except,
+        }
+    }
+}
+
 pub fn transform(input: &[Node], players: &[Player]) -> Vec<TNode> {
+    transform_with(input, &Context::new(players))
+}
+
+pub fn transform_with(input: &[Node], ctx: &Context) -> Vec<TNode> {
+    let nodes = transform_nodes(input, ctx);
+    match ctx.color_blindness {
+        Some(cb) => recolor(nodes, &|c| color::daltonize(&c, cb)),
+        None => nodes,
+    }
+}
+
+/// Maps every color in the transformed nodes.
+fn recolor(nodes: Vec<TNode>, f: &dyn Fn(Color) -> Color) -> Vec<TNode> {
+    nodes
+        .into_iter()
+        .map(|n| match n {
+            TNode::Fg(c, children) => TNode::Fg(f(c), recolor(children, f)),
+            TNode::Bg(c, children) => TNode::Bg(f(c), recolor(children, f)),
+            TNode::Bold(children) => TNode::Bold(recolor(children, f)),
+            TNode::Text(t) => TNode::Text(t),
+        })
+        .collect()
+}
+
+fn transform_nodes(input: &[Node], ctx: &Context) -> Vec<TNode> {
     let mut ret: Vec<TNode> = vec![];
     for n in input {
         match *n {
             // Direct copy nodes.
             Node::Fg(ref c, ref children) => {
-                ret.push(TNode::Fg(c.to_color(players), transform(children, players)))
+                let children = transform_nodes(children, ctx);
+                let bgs = backgrounds(&children, ctx);
+                ret.push(TNode::Fg(c.to_color_over(&bgs, ctx), children))
             }
             Node::Bg(ref c, ref children) => {
-                ret.push(TNode::Bg(c.to_color(players), transform(children, players)))
+                let bg = c.to_color(ctx);
+                ret.push(TNode::Bg(bg, transform_nodes(children, &ctx.with_bg(bg))))
             }
-            Node::Bold(ref children) => ret.push(TNode::Bold(transform(children, players))),
-            Node::Group(ref children) => ret.extend(transform(children, players)),
+            Node::Bold(ref children) => ret.push(TNode::Bold(transform_nodes(children, ctx))),
+            Node::Group(ref children) => ret.extend(transform_nodes(children, ctx)),
             Node::Text(ref t) => ret.push(TNode::Text(t.to_string())),
-            Node::Player(p) => ret.extend(player(p, players)),
-            Node::Align(ref a, w, ref c) => ret.extend(align(a, w, &transform(c, players))),
-            Node::Indent(n, ref c) => ret.extend(indent(n, &transform(c, players))),
-            Node::Table(ref rows) => ret.extend(table(rows, players)),
-            Node::Canvas(ref els) => ret.extend(canvas(els, players)),
+            Node::Player(p) => ret.extend(player(&PlayerRef::new(p), ctx)),
+            Node::PlayerRef(ref r) => ret.extend(player(r, ctx)),
+            Node::Team(t) => ret.extend(team(t, ctx)),
+            Node::Align(ref a, w, ref c) => ret.extend(align(a, w, &transform_nodes(c, ctx))),
+            Node::Indent(n, ref c) => ret.extend(indent(n, &transform_nodes(c, ctx))),
+            Node::Table(ref rows) => ret.extend(table(rows, ctx)),
+            Node::Canvas(ref els) => ret.extend(canvas(els, false, ctx)),
+            Node::MergeCanvas(ref els) => ret.extend(canvas(els, true, ctx)),
+            Node::Gradient(ref from, ref to, bg, ref c) => ret.extend(gradient(
+                &from.to_color(ctx),
+                &to.to_color(ctx),
+                bg,
+                &transform_nodes(c, ctx),
+            )),
+            Node::Flip(ref f, glyphs, ref c) => {
+                ret.extend(flip(f, glyphs, &transform_nodes(c, ctx)))
+            }
+            Node::Board(ref b) => ret.extend(board(b, ctx)),
+            Node::HexGrid(ref orientation, ref hexes) => {
+                ret.extend(hex_grid(orientation, hexes, ctx))
+            }
+            Node::Card(ref rank, ref suit, ref style) => {
+                ret.extend(card(rank, suit, style, ctx))
+            }
+            Node::Die(value, ref style) => ret.extend(die(value, style)),
+            Node::Bar(ref b) => ret.extend(bar(b, ctx)),
+            Node::Sparkline(ref values) => ret.extend(sparkline(values)),
+            Node::BarChart(width, ref bars) => ret.extend(bar_chart(width, bars, ctx)),
+            Node::Only(ref ps, ref c) => if ctx.can_see(ps, false) {
+                ret.extend(transform_nodes(c, ctx))
+            },
+            Node::Except(ref ps, ref c) => if ctx.can_see(ps, true) {
+                ret.extend(transform_nodes(c, ctx))
+            },
         }
     }
     ret
 }
 
-fn player(p: usize, players: &[Player]) -> Vec<TNode> {
-    let p_name = players
-        .get(p)
-        .map(|p| p.name.to_string())
-        .unwrap_or_else(|| format!("Player {}", p));
-    let p_col = players
+/// The distinct backgrounds the given content will be rendered on.
+fn backgrounds(nodes: &[TNode], ctx: &Context) -> Vec<Color> {
+    let mut bgs: Vec<Color> = vec![];
+    for bgr in TNode::bg_ranges(nodes) {
+        let bg = bgr.color.unwrap_or_else(|| ctx.background());
+        if bgr.end > bgr.start && !bgs.contains(&bg) {
+            bgs.push(bg);
+        }
+    }
+    if bgs.is_empty() {
+        bgs.push(ctx.background());
+    }
+    bgs
+}
+
+fn player(r: &PlayerRef, ctx: &Context) -> Vec<TNode> {
+    let p = r.player;
+    let style = ctx.player_style.with_opts(&r.opts);
+    let p_col = ctx.players
         .get(p)
         .map(|p| p.color)
         .unwrap_or_else(|| player_color(p).to_owned());
-    vec![
-        TNode::Bold(vec![
-            TNode::Fg(p_col, vec![TNode::text(format!("<{}>", p_name))]),
-        ]),
-    ]
+    let text = match (ctx.you.as_ref(), ctx.viewer) {
+        (Some(you), Some(Viewer::Player(v))) if v == p => {
+            let word = match r.form {
+                Form::Subject => &you.subject,
+                Form::Possessive => &you.possessive,
+            };
+            if r.cap {
+                capitalize(word)
+            } else {
+                word.to_owned()
+            }
+        }
+        _ => {
+            // The fallback is a label rather than a name, so it isn't shortened.
+            let p_name = ctx.players
+                .get(p)
+                .map(|p| style.format_name(&p.name))
+                .unwrap_or_else(|| style.fallback.replace("{}", &p.to_string()));
+            let mut text = if style.brackets {
+                format!("<{}>", p_name)
+            } else {
+                p_name
+            };
+            if style.icon {
+                text = format!("{}{}", player_symbol(p), text);
+            }
+            if r.form == Form::Possessive {
+                text.push_str("'s");
+            }
+            text
+        }
+    };
+    let node = TNode::Fg(p_col, vec![TNode::text(text)]);
+    if style.bold {
+        vec![TNode::Bold(vec![node])]
+    } else {
+        vec![node]
+    }
 }
 
-fn table(rows: &[Row], players: &[Player]) -> Vec<TNode> {
+fn team(t: usize, ctx: &Context) -> Vec<TNode> {
+    let (name, col) = ctx.teams
+        .get(t)
+        .map(|t| (t.name.to_string(), t.color))
+        .unwrap_or_else(|| (format!("Team {}", t), player_color(t).to_owned()));
+    vec![TNode::Bold(vec![TNode::Fg(col, vec![TNode::text(name)])])]
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn table(rows: &[Row], ctx: &Context) -> Vec<TNode> {
     // Transform individual cells and calculate row heights and column widths.
     let mut transformed: Vec<Vec<Vec<Vec<TNode>>>> = vec![];
     let mut widths: Vec<usize> = vec![];
@@ -78,7 +503,7 @@ fn table(rows: &[Row], players: &[Player]) -> Vec<TNode> {
         let mut row: Vec<Vec<Vec<TNode>>> = vec![];
         let mut row_height: usize = 1;
         for (i, &(_, ref children)) in r.iter().enumerate() {
-            let cell_lines = to_lines(&transform(children, players));
+            let cell_lines = to_lines(&transform_nodes(children, ctx));
             row_height = cmp::max(row_height, cell_lines.len());
             let width = cell_lines
                 .iter()
@@ -116,6 +541,363 @@ fn table(rows: &[Row], players: &[Player]) -> Vec<TNode> {
     output
 }
 
+fn board(b: &Board, ctx: &Context) -> Vec<TNode> {
+    let light = b.light.to_color(ctx);
+    let dark = b.dark.to_color(ctx);
+    let height = cmp::max(b.cell_height, 1);
+    let cols = b.rows.iter().fold(0, |cols, r| cmp::max(cols, r.len()));
+    let rank_labels: Vec<String> = (0..b.rows.len())
+        .map(|ri| match b.labels {
+            BoardLabels::None => "".to_string(),
+            BoardLabels::Chess => (b.rows.len() - ri).to_string(),
+            BoardLabels::Numeric => (ri + 1).to_string(),
+        })
+        .collect();
+    let label_width = rank_labels
+        .iter()
+        .fold(0, |width, l| cmp::max(width, l.chars().count()));
+    let cell_bg = |ri: usize, ci: usize| if (ri + ci) % 2 == 0 { light } else { dark };
+    let mut lines: Vec<Vec<TNode>> = vec![];
+    for (ri, r) in b.rows.iter().enumerate() {
+        // Cells are transformed over their own background so contrast colors
+        // stay readable on both light and dark cells.
+        let cells: Vec<(&Align, Vec<Vec<TNode>>)> = r.iter()
+            .enumerate()
+            .map(|(ci, &(ref al, ref children))| {
+                (
+                    al,
+                    to_lines(&transform_nodes(children, &ctx.with_bg(cell_bg(ri, ci)))),
+                )
+            })
+            .collect();
+        for line_i in 0..height {
+            let mut line: Vec<TNode> = vec![];
+            if label_width > 0 {
+                let label = if line_i == (height - 1) / 2 {
+                    vec![TNode::text(rank_labels[ri].to_owned())]
+                } else {
+                    vec![]
+                };
+                line.extend(align(&Align::Right, label_width, &label));
+                line.push(TNode::text(" "));
+            }
+            for ci in 0..cols {
+                let bg = cell_bg(ri, ci);
+                let content = cells.get(ci).and_then(|&(al, ref cell_lines)| {
+                    // Center content vertically, clipping it to the cell.
+                    let top = (height - cmp::min(cell_lines.len(), height)) / 2;
+                    if line_i < top {
+                        return None;
+                    }
+                    cell_lines
+                        .get(line_i - top)
+                        .map(|l| (al, slice(l, &(0..b.cell_width))))
+                });
+                line.push(TNode::Bg(
+                    bg,
+                    match content {
+                        Some((al, l)) => align(al, b.cell_width, &l),
+                        None => align(&Align::Left, b.cell_width, &[]),
+                    },
+                ));
+            }
+            lines.push(line);
+        }
+    }
+    if label_width > 0 {
+        let mut line: Vec<TNode> = vec![TNode::Text(" ".repeat(label_width + 1))];
+        for ci in 0..cols {
+            let label = match b.labels {
+                BoardLabels::Chess if ci < 26 => ((b'a' + ci as u8) as char).to_string(),
+                _ => (ci + 1).to_string(),
+            };
+            line.extend(align(&Align::Center, b.cell_width, &[TNode::text(label)]));
+        }
+        lines.push(line);
+    }
+    from_lines(&lines)
+}
+
+enum HexPart {
+    Border,
+    Fill,
+    /// Bottom border which also sits on the fill color.
+    Base,
+}
+
+const FLAT_HEX: &[(usize, usize, HexPart, &str)] = &[
+    (2, 0, HexPart::Border, "_____"),
+    (1, 1, HexPart::Border, "/"),
+    (2, 1, HexPart::Fill, "     "),
+    (7, 1, HexPart::Border, "\\"),
+    (0, 2, HexPart::Border, "/"),
+    (1, 2, HexPart::Fill, "       "),
+    (8, 2, HexPart::Border, "\\"),
+    (0, 3, HexPart::Border, "\\"),
+    (1, 3, HexPart::Fill, "       "),
+    (8, 3, HexPart::Border, "/"),
+    (1, 4, HexPart::Border, "\\"),
+    (2, 4, HexPart::Base, "_____"),
+    (7, 4, HexPart::Border, "/"),
+];
+
+const POINTY_HEX: &[(usize, usize, HexPart, &str)] = &[
+    (1, 0, HexPart::Border, "/"),
+    (2, 0, HexPart::Fill, " "),
+    (3, 0, HexPart::Border, "\\"),
+    (0, 1, HexPart::Border, "|"),
+    (1, 1, HexPart::Fill, "   "),
+    (4, 1, HexPart::Border, "|"),
+    (0, 2, HexPart::Border, "|"),
+    (1, 2, HexPart::Fill, "   "),
+    (4, 2, HexPart::Border, "|"),
+    (1, 3, HexPart::Border, "\\"),
+    (2, 3, HexPart::Fill, " "),
+    (3, 3, HexPart::Border, "/"),
+];
+
+/// Lays out hexes as canvas layers.  Each part of a hex is its own layer so
+/// the space around a hex doesn't overwrite its neighbours, and content is
+/// centered in the middle two lines where it inherits the fill color.
+fn hex_grid(orientation: &HexOrientation, hexes: &[Hex], ctx: &Context) -> Vec<TNode> {
+    let (parts, content_x, content_y, content_width, content_height) = match *orientation {
+        HexOrientation::Flat => (FLAT_HEX, 1, 2, 7, 2),
+        HexOrientation::Pointy => (POINTY_HEX, 1, 1, 3, 2),
+    };
+    let mut layers: Vec<(usize, usize, Vec<TNode>)> = vec![];
+    for h in hexes {
+        let (x, y) = match *orientation {
+            HexOrientation::Flat => (h.col * 7, h.row * 4 + h.col % 2 * 2),
+            HexOrientation::Pointy => (h.col * 4 + h.row % 2 * 2, h.row * 3),
+        };
+        let with_border = |nodes: Vec<Node>| match h.border {
+            Some(ref c) => vec![Node::Fg(c.clone(), nodes)],
+            None => nodes,
+        };
+        let with_fill = |nodes: Vec<Node>| match h.fill {
+            Some(ref c) => vec![Node::Bg(c.clone(), nodes)],
+            None => nodes,
+        };
+        for &(dx, dy, ref part, text) in parts {
+            let nodes = vec![Node::text(text)];
+            layers.push((
+                x + dx,
+                y + dy,
+                transform_nodes(
+                    &match *part {
+                        HexPart::Border => with_border(nodes),
+                        HexPart::Fill => with_fill(nodes),
+                        HexPart::Base => with_fill(with_border(nodes)),
+                    },
+                    ctx,
+                ),
+            ));
+        }
+        let content_ctx = match h.fill {
+            Some(ref c) => ctx.with_bg(c.to_color(ctx)),
+            None => ctx.clone(),
+        };
+        // Clip content to the inside of the hex so it can't break the border.
+        let content: Vec<Vec<TNode>> = to_lines(&transform_nodes(&h.content, &content_ctx))
+            .iter()
+            .take(content_height)
+            .map(|l| slice(l, &(0..content_width)))
+            .collect();
+        layers.push((
+            x + content_x,
+            y + content_y,
+            align(&Align::Center, content_width, &from_lines(&content)),
+        ));
+    }
+    canvas_layers(&layers, false)
+}
+
+fn card(rank: &str, suit: &Suit, style: &GlyphStyle, ctx: &Context) -> Vec<TNode> {
+    let color = suit.col().to_color(ctx);
+    match *style {
+        GlyphStyle::Compact => vec![
+            TNode::Bold(vec![
+                TNode::Fg(color, vec![TNode::text(format!("{}{}", rank, suit.symbol()))]),
+            ]),
+        ],
+        GlyphStyle::Text => vec![TNode::text(format!("{}{}", rank, suit.letter()))],
+        GlyphStyle::Large => {
+            let fg = |text: String| vec![TNode::Fg(color, vec![TNode::text(text)])];
+            // Long ranks are clipped so they can't break the frame.
+            let rank: String = rank.chars().take(3).collect();
+            let mut lines: Vec<Vec<TNode>> = vec![vec![TNode::text("┌───┐")]];
+            for content in &[
+                align(&Align::Left, 3, &fg(rank.clone())),
+                align(&Align::Center, 3, &fg(suit.symbol().to_string())),
+                align(&Align::Right, 3, &fg(rank.clone())),
+            ] {
+                let mut line = vec![TNode::text("│")];
+                line.extend(content.to_owned());
+                line.push(TNode::text("│"));
+                lines.push(line);
+            }
+            lines.push(vec![TNode::text("└───┘")]);
+            from_lines(&lines)
+        }
+    }
+}
+
+fn die(value: usize, style: &GlyphStyle) -> Vec<TNode> {
+    match *style {
+        GlyphStyle::Compact => vec![
+            TNode::text(match value {
+                1 => "⚀".to_string(),
+                2 => "⚁".to_string(),
+                3 => "⚂".to_string(),
+                4 => "⚃".to_string(),
+                5 => "⚄".to_string(),
+                6 => "⚅".to_string(),
+                _ => format!("[{}]", value),
+            }),
+        ],
+        GlyphStyle::Text => vec![TNode::text(format!("[{}]", value))],
+        GlyphStyle::Large => {
+            // Pips are laid out on a 3x3 grid, indexed left to right and top
+            // to bottom.
+            let pips: &[usize] = match value {
+                1 => &[4],
+                2 => &[0, 8],
+                3 => &[0, 4, 8],
+                4 => &[0, 2, 6, 8],
+                5 => &[0, 2, 4, 6, 8],
+                6 => &[0, 2, 3, 5, 6, 8],
+                _ => &[],
+            };
+            let mut lines = vec!["┌───────┐".to_string()];
+            for row in 0..3 {
+                lines.push(if pips.is_empty() && row == 1 {
+                    format!(
+                        "│{:^7}│",
+                        value.to_string().chars().take(7).collect::<String>()
+                    )
+                } else {
+                    format!(
+                        "│ {} │",
+                        (0..3)
+                            .map(|col| if pips.contains(&(row * 3 + col)) {
+                                "●"
+                            } else {
+                                " "
+                            })
+                            .collect::<Vec<&str>>()
+                            .join(" ")
+                    )
+                });
+            }
+            lines.push("└───────┘".to_string());
+            vec![TNode::text(lines.join("\n"))]
+        }
+    }
+}
+
+/// Partial blocks in eighths, indexed by the number of eighths filled.
+const EIGHTHS: [&str; 8] = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉"];
+
+fn bar(b: &Bar, ctx: &Context) -> Vec<TNode> {
+    let width = if b.text {
+        b.width.saturating_sub(2)
+    } else {
+        b.width
+    };
+    let filled = bar_fill(b.value, b.max, width, b.text);
+    let remaining = width - filled.chars().count();
+    let mut nodes = vec![];
+    if !filled.is_empty() {
+        nodes.push(TNode::Fg(b.fill.to_color(ctx), vec![TNode::Text(filled)]));
+    }
+    if remaining > 0 {
+        nodes.push(TNode::Fg(
+            b.empty.to_color(ctx),
+            vec![TNode::Text((if b.text { "-" } else { "░" }).repeat(remaining))],
+        ));
+    }
+    if b.text {
+        nodes.insert(0, TNode::text("["));
+        nodes.push(TNode::text("]"));
+    }
+    nodes
+}
+
+/// The filled part of a bar showing `value` out of `max` across `width`
+/// characters.
+fn bar_fill(value: usize, max: usize, width: usize, text: bool) -> String {
+    // Wide integers keep large values and widths from overflowing.
+    let eighths = ((cmp::min(value, max) as u128).saturating_mul(width as u128 * 8)
+        + max as u128 / 2)
+        .checked_div(max as u128)
+        .unwrap_or(0) as usize;
+    if text {
+        // ASCII can't show partial blocks, so round to the nearest character.
+        return "#".repeat((eighths + 4) / 8);
+    }
+    format!("{}{}", "█".repeat(eighths / 8), EIGHTHS[eighths % 8])
+}
+
+/// Levels used by sparklines, from lowest to highest.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+fn sparkline(values: &[i64]) -> Vec<TNode> {
+    let min = values.iter().cloned().min().unwrap_or(0);
+    let max = values.iter().cloned().max().unwrap_or(0);
+    // Differences are taken in i128 as they can exceed the range of i64.
+    let range = (i128::from(max) - i128::from(min)) as f64;
+    vec![
+        TNode::Text(
+            values
+                .iter()
+                .map(|&v| if range == 0.0 {
+                    SPARKS[0]
+                } else {
+                    SPARKS[((i128::from(v) - i128::from(min)) as f64 / range * 7.0).round()
+                        as usize]
+                })
+                .collect(),
+        ),
+    ]
+}
+
+/// Bar charts are laid out as a table of labels, bars and right aligned
+/// values, with bars scaled relative to the largest value.
+fn bar_chart(width: usize, bars: &[ChartBar], ctx: &Context) -> Vec<TNode> {
+    let max = bars.iter().fold(0, |max, b| cmp::max(max, b.value));
+    table(
+        &bars
+            .iter()
+            .map(|b| {
+                row_pad(
+                    &[
+                        (Align::Left, b.label.clone()),
+                        (
+                            Align::Left,
+                            vec![
+                                Node::Fg(
+                                    b.color.clone(),
+                                    vec![
+                                        Node::Text(bar_fill(
+                                            cmp::max(b.value, 0) as usize,
+                                            max as usize,
+                                            width,
+                                            false,
+                                        )),
+                                    ],
+                                ),
+                            ],
+                        ),
+                        (Align::Right, vec![Node::text(b.value.to_string())]),
+                    ],
+                    " ",
+                )
+            })
+            .collect::<Vec<Row>>(),
+        ctx,
+    )
+}
+
 fn align(a: &Align, width: usize, children: &[TNode]) -> Vec<TNode> {
     let mut aligned: Vec<TNode> = vec![];
     for l in to_lines(children) {
@@ -164,6 +946,97 @@ fn indent(n: usize, children: &[TNode]) -> Vec<TNode> {
         .collect::<Vec<Vec<TNode>>>())
 }
 
+fn flip(f: &Flip, glyphs: bool, children: &[TNode]) -> Vec<TNode> {
+    let (horizontal, vertical) = match *f {
+        Flip::Horizontal => (true, false),
+        Flip::Vertical => (false, true),
+        Flip::Rotate => (true, true),
+    };
+    let lines = to_lines(children);
+    let width = lines
+        .iter()
+        .fold(0, |width, l| cmp::max(width, TNode::len(l)));
+    let mut flipped: Vec<Vec<TNode>> = lines
+        .iter()
+        .map(|l| {
+            let l = if horizontal {
+                // Pad lines to the same width so they stay aligned on the right.
+                reverse(&align(&Align::Left, width, l))
+            } else {
+                l.to_owned()
+            };
+            if !glyphs {
+                return l;
+            }
+            map_chars(&l, &mut 0, &mut |_, c| {
+                let c = if horizontal { mirror::horizontal(c) } else { c };
+                if vertical {
+                    mirror::vertical(c)
+                } else {
+                    c
+                }
+            })
+        })
+        .collect();
+    if vertical {
+        flipped.reverse();
+    }
+    from_lines(&flipped)
+}
+
+/// The color `i` steps along a gradient of `steps` colors.
+fn gradient_step(from: &Color, to: &Color, i: usize, steps: usize) -> Color {
+    if steps < 2 {
+        return *from;
+    }
+    color::mix(from, to, (i * 100 / (steps - 1)) as u8)
+}
+
+fn gradient(from: &Color, to: &Color, bg: bool, children: &[TNode]) -> Vec<TNode> {
+    let lines = to_lines(children);
+    if bg {
+        let steps = lines.len();
+        return from_lines(&lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, l)| vec![TNode::Bg(gradient_step(from, to, i, steps), l)])
+            .collect::<Vec<Vec<TNode>>>());
+    }
+    // Foreground gradients run across the widest line so lines line up.
+    let width = lines.iter().map(|l| TNode::len(l)).max().unwrap_or(0);
+    from_lines(&lines
+        .iter()
+        .map(|l| {
+            // Group neighbouring characters which end up the same color.
+            let mut runs: Vec<(Color, Range<usize>)> = vec![];
+            for i in 0..TNode::len(l) {
+                let c = gradient_step(from, to, i, width);
+                match runs.last_mut() {
+                    Some(&mut (last, ref mut r)) if last == c => r.end = i + 1,
+                    _ => runs.push((c, i..i + 1)),
+                }
+            }
+            runs.into_iter()
+                .map(|(c, r)| TNode::Fg(c, slice(l, &r)))
+                .collect()
+        })
+        .collect::<Vec<Vec<TNode>>>())
+}
+
+/// Reverses the order of characters in a line, preserving styling.
+fn reverse(nodes: &[TNode]) -> Vec<TNode> {
+    nodes
+        .iter()
+        .rev()
+        .map(|n| match *n {
+            TNode::Fg(color, ref children) => TNode::Fg(color, reverse(children)),
+            TNode::Bg(color, ref children) => TNode::Bg(color, reverse(children)),
+            TNode::Bold(ref children) => TNode::Bold(reverse(children)),
+            TNode::Text(ref text) => TNode::Text(text.chars().rev().collect()),
+        })
+        .collect()
+}
+
 /// `to_lines` splits text nodes into multiple text nodes, duplicating parent
 /// nodes as necessary.
 pub fn to_lines(nodes: &[TNode]) -> Vec<Vec<TNode>> {
@@ -226,7 +1099,7 @@ fn slice(nodes: &[TNode], range: &Range<usize>) -> Vec<TNode> {
     let mut end = range.end;
     for n in nodes {
         let n_len = TNode::len(&[n.clone()]);
-        if n_len < start {
+        if n_len <= start {
             start -= n_len;
             end -= n_len;
             continue;
@@ -236,7 +1109,7 @@ fn slice(nodes: &[TNode], range: &Range<usize>) -> Vec<TNode> {
             TNode::Bg(ref color, ref children) => TNode::Bg(*color, slice(children, &(start..end))),
             TNode::Bold(ref children) => TNode::Bold(slice(children, &(start..end))),
             TNode::Text(ref text) => {
-                TNode::Text(text[start..cmp::min(text.len(), end)].to_string())
+                TNode::Text(text.chars().skip(start).take(end - start).collect())
             }
         };
 
@@ -276,18 +1149,113 @@ fn bg_ranges_slice(bgrs: &[BgRange], range: &Range<usize>) -> Vec<BgRange> {
         .collect()
 }
 
-fn canvas(els: &[(usize, usize, Vec<Node>)], players: &[Player]) -> Vec<TNode> {
+/// Like `bg_ranges_slice`, but fills gaps with uncolored ranges so the whole
+/// range is covered.
+fn bg_ranges_cover(bgrs: &[BgRange], range: &Range<usize>) -> Vec<BgRange> {
+    let mut sliced = bg_ranges_slice(bgrs, range);
+    sliced.sort_by_key(|bgr| bgr.start);
+    let mut covered = vec![];
+    let mut pos = range.start;
+    for bgr in sliced {
+        if bgr.start > pos {
+            covered.push(BgRange {
+                start: pos,
+                end: bgr.start,
+                color: None,
+            });
+        }
+        pos = bgr.end;
+        covered.push(bgr);
+    }
+    if pos < range.end {
+        covered.push(BgRange {
+            start: pos,
+            end: range.end,
+            color: None,
+        });
+    }
+    covered
+}
+
+/// Maps each character in the nodes, passing in the character offset.
+fn map_chars<F>(nodes: &[TNode], offset: &mut usize, f: &mut F) -> Vec<TNode>
+where
+    F: FnMut(usize, char) -> char,
+{
+    let mut mapped = vec![];
+    for n in nodes {
+        mapped.push(match *n {
+            TNode::Fg(color, ref children) => TNode::Fg(color, map_chars(children, offset, f)),
+            TNode::Bg(color, ref children) => TNode::Bg(color, map_chars(children, offset, f)),
+            TNode::Bold(ref children) => TNode::Bold(map_chars(children, offset, f)),
+            TNode::Text(ref text) => {
+                let mut new_text = String::new();
+                for c in text.chars() {
+                    new_text.push(f(*offset, c));
+                    *offset += 1;
+                }
+                TNode::Text(new_text)
+            }
+        });
+    }
+    mapped
+}
+
+fn canvas_char_at(cl: &[(usize, Vec<TNode>)], x: usize) -> Option<char> {
+    cl.iter()
+        .find(|&&(offset, ref els)| x >= offset && x < offset + TNode::len(els))
+        .and_then(|&(offset, ref els)| {
+            plain::render(&slice(els, &(x - offset..x - offset + 1)))
+                .chars()
+                .next()
+        })
+}
+
+/// Merges box drawing characters in a new canvas line with those already on
+/// the canvas underneath it, so borders drawn by separate layers join up.
+fn canvas_merge_boxes(cl: &[(usize, Vec<TNode>)], x: usize, line: &[TNode]) -> Vec<TNode> {
+    map_chars(line, &mut 0, &mut |i, c| {
+        if !box_drawing::is_box(c) {
+            return c;
+        }
+        canvas_char_at(cl, x + i)
+            .and_then(|ex_c| box_drawing::merge(ex_c, c))
+            .unwrap_or(c)
+    })
+}
+
+/// Lays out layers at absolute positions, with later layers overwriting
+/// earlier ones.  If `merge` is set, overlapping box drawing characters are
+/// combined into junctions instead of being overwritten.
+fn canvas(els: &[(usize, usize, Vec<Node>)], merge: bool, ctx: &Context) -> Vec<TNode> {
+    canvas_layers(
+        &els.iter()
+            .map(|&(x, y, ref nodes)| (x, y, transform_nodes(nodes, ctx)))
+            .collect::<Vec<(usize, usize, Vec<TNode>)>>(),
+        merge,
+    )
+}
+
+/// Lays out already transformed layers, as for `canvas`.
+fn canvas_layers(els: &[(usize, usize, Vec<TNode>)], merge: bool) -> Vec<TNode> {
     // Output is split into lines each with a start position.
     let mut lines: Vec<Vec<(usize, Vec<TNode>)>> = vec![];
     for &(x, y, ref nodes) in els {
         let lines_len = lines.len();
-        let node_lines = to_lines(&transform(nodes, players));
+        let node_lines = to_lines(nodes);
         let node_lines_len = node_lines.len();
         if y + node_lines_len > lines_len {
             lines.extend(iter::repeat(vec![]).take(y + node_lines_len - lines_len));
         }
-        for (n_i, orig_n_line) in node_lines.iter().enumerate() {
+        for (n_i, node_line) in node_lines.iter().enumerate() {
             let n_line_y = y + n_i;
+            let merged_line;
+            let orig_n_line = if merge {
+                merged_line = canvas_merge_boxes(&lines[n_line_y], x, node_line);
+                &merged_line
+            } else {
+                node_line
+            };
             let n_line_len = TNode::len(orig_n_line);
             // Inherit background colors from existing lines if required.
             let ex_n_line_bgrs = canvas_line_bg_ranges(&lines[n_line_y]);
@@ -295,7 +1263,7 @@ fn canvas(els: &[(usize, usize, Vec<Node>)], players: &[Player]) -> Vec<TNode> {
                 .iter()
                 .flat_map(|bgr| match bgr.color {
                     Some(_) => slice(orig_n_line, &(bgr.start..bgr.end)),
-                    None => bg_ranges_slice(&ex_n_line_bgrs, &(bgr.start + x..bgr.end + x))
+                    None => bg_ranges_cover(&ex_n_line_bgrs, &(bgr.start + x..bgr.end + x))
                         .iter()
                         .flat_map(|ex_n_line_bgr| {
                             let n_slice = slice(
@@ -372,6 +1340,30 @@ mod tests {
     use crate::plain::render;
     use crate::ast::{Align as A, Node as N, TNode as TN};
 
+    #[test]
+    fn canvas_works() {
+        // Uncolored text is kept over empty parts of the canvas, and takes the
+        // background of layers underneath it.
+        let input = vec![
+            N::Canvas(vec![
+                (0, 0, vec![N::Bg(RED.into(), vec![N::text("    ")])]),
+                (2, 0, vec![N::text("abcd")]),
+                (0, 1, vec![N::text("ef")]),
+            ]),
+        ];
+        assert_eq!("  abcd\nef".to_string(), render(&transform(&input, &[])));
+        assert_eq!(
+            vec![
+                TN::Bg(RED, vec![TN::text("  ")]),
+                TN::Bg(RED, vec![TN::text("ab")]),
+                TN::text("cd"),
+                TN::text("\n"),
+                TN::text("ef"),
+            ],
+            transform(&input, &[])
+        );
+    }
+
     #[test]
     fn align_works() {
         assert_eq!(
@@ -436,6 +1428,665 @@ mod tests {
         );
     }
 
+    #[test]
+    fn merge_canvas_works() {
+        let cell = vec![N::text("┌─┐\n└─┘")];
+        assert_eq!(
+            "┌─┬─┐\n└─┴─┘".to_string(),
+            render(&transform(
+                &vec![N::MergeCanvas(vec![(0, 0, cell.clone()), (2, 0, cell.clone())])],
+                &[],
+            ))
+        );
+        assert_eq!(
+            "┌─┌─┐\n└─└─┘".to_string(),
+            render(&transform(
+                &vec![N::Canvas(vec![(0, 0, cell.clone()), (2, 0, cell.clone())])],
+                &[],
+            ))
+        );
+    }
+
+    #[test]
+    fn flip_works() {
+        let board = vec![N::text("┌→\n│ab")];
+        assert_eq!(
+            " ←┐\nba│".to_string(),
+            render(&transform(&[N::Flip(Flip::Horizontal, true, board.clone())], &[]))
+        );
+        assert_eq!(
+            "│ab\n┌→".to_string(),
+            render(&transform(&[N::Flip(Flip::Vertical, false, board.clone())], &[]))
+        );
+        assert_eq!(
+            "ba│\n ←┘".to_string(),
+            render(&transform(&[N::Flip(Flip::Rotate, true, board.clone())], &[]))
+        );
+        assert_eq!(
+            transform(
+                &[N::Flip(Flip::Horizontal, false, vec![N::Fg(RED.into(), vec![N::text("ab")])])],
+                &[],
+            ),
+            vec![TN::Fg(RED, vec![TN::text("ba")])]
+        );
+    }
+
+    #[test]
+    fn board_works() {
+        let b = |labels| {
+            N::Board(Board {
+                rows: vec![
+                    vec![(A::Center, vec![N::text("x")]), (A::Center, vec![])],
+                    vec![(A::Center, vec![]), (A::Left, vec![N::text("o")])],
+                ],
+                cell_width: 3,
+                cell_height: 1,
+                light: WHITE.into(),
+                dark: GREY.into(),
+                labels,
+            })
+        };
+        assert_eq!(
+            " x    \n   o  ".to_string(),
+            render(&transform(&[b(BoardLabels::None)], &[]))
+        );
+        assert_eq!(
+            "2  x    \n1    o  \n   a  b ".to_string(),
+            render(&transform(&[b(BoardLabels::Chess)], &[]))
+        );
+        assert_eq!(
+            "1  x    \n2    o  \n   1  2 ".to_string(),
+            render(&transform(&[b(BoardLabels::Numeric)], &[]))
+        );
+        // Chess files past z fall back to numbers.
+        let wide = N::Board(Board {
+            rows: vec![vec![(A::Left, vec![]); 27]],
+            cell_width: 2,
+            cell_height: 1,
+            light: WHITE.into(),
+            dark: GREY.into(),
+            labels: BoardLabels::Chess,
+        });
+        assert!(render(&transform(&[wide], &[])).ends_with("y z 27"));
+    }
+
+    #[test]
+    fn hex_grid_works() {
+        let hex = |col, row, content: &str| Hex {
+            col,
+            row,
+            fill: None,
+            border: None,
+            content: vec![N::text(content)],
+        };
+        assert_eq!(
+            [
+                "  _____",
+                " /     \\",
+                "/   a   \\_____",
+                "\\       /     \\",
+                " \\_____/   b   \\",
+                "       \\       /",
+                "        \\_____/",
+            ].join("\n"),
+            render(&transform(
+                &[N::HexGrid(HexOrientation::Flat, vec![hex(0, 0, "a"), hex(1, 0, "b")])],
+                &[],
+            ))
+        );
+        assert_eq!(
+            [
+                " / \\ / \\",
+                "| a | b |",
+                "|   |   |",
+                " \\ / \\ /",
+                "  | c |",
+                "  |   |",
+                "   \\ /",
+            ].join("\n"),
+            render(&transform(
+                &[N::HexGrid(
+                    HexOrientation::Pointy,
+                    vec![hex(0, 0, "a"), hex(1, 0, "b"), hex(0, 1, "c")],
+                )],
+                &[],
+            ))
+        );
+        // Content wider or taller than the hex is clipped.
+        assert_eq!(
+            ["  _____", " /     \\", "/   a   \\", "\\   b   /", " \\_____/"].join("\n"),
+            render(&transform(
+                &[N::HexGrid(HexOrientation::Flat, vec![hex(0, 0, "a\nb\nc\nd")])],
+                &[],
+            ))
+        );
+        assert_eq!(
+            [" / \\ / \\", "|lon| b |", "|   |   |", " \\ / \\ /"].join("\n"),
+            render(&transform(
+                &[N::HexGrid(HexOrientation::Pointy, vec![hex(0, 0, "long"), hex(1, 0, "b")])],
+                &[],
+            ))
+        );
+    }
+
+    #[test]
+    fn card_works() {
+        assert_eq!(
+            transform(&[N::Card("Q".to_string(), Suit::Hearts, GlyphStyle::Compact)], &[]),
+            vec![TN::Bold(vec![TN::Fg(RED, vec![TN::text("Q♥")])])]
+        );
+        assert_eq!(
+            "10S".to_string(),
+            render(&transform(
+                &[N::Card("10".to_string(), Suit::Spades, GlyphStyle::Text)],
+                &[],
+            ))
+        );
+        assert_eq!(
+            "┌───┐\n│10 │\n│ ♠ │\n│ 10│\n└───┘".to_string(),
+            render(&transform(
+                &[N::Card("10".to_string(), Suit::Spades, GlyphStyle::Large)],
+                &[],
+            ))
+        );
+        assert_eq!(
+            "┌───┐\n│Que│\n│ ♠ │\n│Que│\n└───┘".to_string(),
+            render(&transform(
+                &[N::Card("Queen".to_string(), Suit::Spades, GlyphStyle::Large)],
+                &[],
+            ))
+        );
+        let spades = [N::Card("A".to_string(), Suit::Spades, GlyphStyle::Compact)];
+        assert_eq!(
+            vec![TN::Bold(vec![TN::Fg(BLACK, vec![TN::text("A♠")])])],
+            transform(&spades, &[])
+        );
+        assert_eq!(
+            vec![TN::Bold(vec![TN::Fg(WHITE, vec![TN::text("A♠")])])],
+            transform_with(&spades, &Context::new(&[]).theme(Theme::dark()))
+        );
+    }
+
+    #[test]
+    fn die_works() {
+        assert_eq!(
+            "⚄ [5] [9]".to_string(),
+            render(&transform(
+                &[
+                    N::Die(5, GlyphStyle::Compact),
+                    N::text(" "),
+                    N::Die(5, GlyphStyle::Text),
+                    N::text(" "),
+                    N::Die(9, GlyphStyle::Compact),
+                ],
+                &[],
+            ))
+        );
+        assert_eq!(
+            "┌───────┐\n│ ●   ● │\n│   ●   │\n│ ●   ● │\n└───────┘".to_string(),
+            render(&transform(&[N::Die(5, GlyphStyle::Large)], &[]))
+        );
+        assert_eq!(
+            "┌───────┐\n│       │\n│1234567│\n│       │\n└───────┘".to_string(),
+            render(&transform(&[N::Die(123_456_789, GlyphStyle::Large)], &[]))
+        );
+    }
+
+    #[test]
+    fn bar_works() {
+        assert_eq!(
+            transform(&[N::Bar(Bar::new(9, 16, 5))], &[]),
+            vec![
+                TN::Fg(GREEN, vec![TN::text("██▉")]),
+                TN::Fg(GREY, vec![TN::text("░░")]),
+            ]
+        );
+        let mut text_bar = Bar::new(9, 16, 5);
+        text_bar.text = true;
+        assert_eq!(
+            "[##-]".to_string(),
+            render(&transform(&[N::Bar(text_bar)], &[]))
+        );
+        assert_eq!(
+            vec![
+                TN::Fg(Theme::dark().success, vec![TN::text("█")]),
+                TN::Fg(Theme::dark().muted, vec![TN::text("░")]),
+            ],
+            transform_with(
+                &[N::Bar(Bar::new(1, 2, 2))],
+                &Context::new(&[]).theme(Theme::dark())
+            )
+        );
+        assert_eq!(
+            "█████ ░░░░░".to_string(),
+            render(&transform(
+                &[N::Bar(Bar::new(12, 10, 5)), N::text(" "), N::Bar(Bar::new(0, 0, 5))],
+                &[],
+            ))
+        );
+        assert_eq!(
+            "██░░".to_string(),
+            render(&transform(&[N::Bar(Bar::new(usize::MAX / 2, usize::MAX, 4))], &[]))
+        );
+    }
+
+    #[test]
+    fn sparkline_works() {
+        assert_eq!(
+            vec![TN::text("▁▃▂█▅")],
+            transform(&[N::Sparkline(vec![-2, 1, 0, 8, 4])], &[])
+        );
+        assert_eq!(
+            vec![TN::text("▁▁")],
+            transform(&[N::Sparkline(vec![3, 3])], &[])
+        );
+        assert_eq!(
+            vec![TN::text("▁▅█")],
+            transform(&[N::Sparkline(vec![i64::MIN, 0, i64::MAX])], &[])
+        );
+    }
+
+    #[test]
+    fn bar_chart_works() {
+        let chart = N::BarChart(
+            4,
+            vec![
+                ChartBar {
+                    label: vec![N::Player(0)],
+                    value: 10,
+                    color: 0.into(),
+                },
+                ChartBar {
+                    label: vec![N::text("Bob")],
+                    value: 5,
+                    color: BLUE.into(),
+                },
+                ChartBar {
+                    label: vec![N::text("Eve")],
+                    value: -3,
+                    color: RED.into(),
+                },
+            ],
+        );
+        assert_eq!(
+            "<Player 0> ████ 10\nBob        ██    5\nEve             -3".to_string(),
+            render(&transform(&[chart], &[]))
+        );
+    }
+
+    #[test]
+    fn visibility_works() {
+        let log = vec![
+            N::text("a"),
+            N::Only(vec![0, 2], vec![N::text("b")]),
+            N::Except(vec![1], vec![N::text("c")]),
+        ];
+        let players = vec![];
+        let render_for = |viewer: Option<Viewer>| {
+            let mut ctx = Context::new(&players);
+            ctx.viewer = viewer;
+            render(&transform_with(&log, &ctx))
+        };
+        assert_eq!("ac", render_for(None));
+        assert_eq!("abc", render_for(Some(Viewer::Player(0))));
+        assert_eq!("a", render_for(Some(Viewer::Player(1))));
+        assert_eq!("ac", render_for(Some(Viewer::Player(3))));
+        assert_eq!("ac", render_for(Some(Viewer::Spectator)));
+        // Plain transforms are public, so restricted content is dropped.
+        assert_eq!(
+            "pub ",
+            render(&transform(
+                &[N::text("pub "), N::Only(vec![1], vec![N::text("secret")])],
+                &[],
+            ))
+        );
+    }
+
+    #[test]
+    fn you_works() {
+        let players = vec![
+            Player {
+                name: "Ann".to_string(),
+                color: GREEN,
+            },
+            Player {
+                name: "Bob".to_string(),
+                color: RED,
+            },
+        ];
+        let log = vec![
+            N::PlayerRef(PlayerRef::new(0).cap()),
+            N::text(" took "),
+            N::PlayerRef(PlayerRef::new(1).possessive()),
+            N::text(" card, "),
+            N::Player(1),
+            N::text(" took "),
+            N::PlayerRef(PlayerRef::new(0).possessive()),
+            N::text(" card"),
+        ];
+        let ctx = Context::new(&players).viewer(Viewer::Player(0));
+        assert_eq!(
+            "<Ann> took <Bob>'s card, <Bob> took <Ann>'s card",
+            render(&transform_with(&log, &ctx))
+        );
+        let ctx = ctx.you(You::default());
+        assert_eq!(
+            "You took <Bob>'s card, <Bob> took your card",
+            render(&transform_with(&log, &ctx))
+        );
+    }
+
+    #[test]
+    fn col_trans_works() {
+        let players = vec![
+            Player {
+                name: "Ann".to_string(),
+                color: Color { r: 100, g: 0, b: 200 },
+            },
+        ];
+        let ctx = Context::new(&players);
+        let col: Col = 0.into();
+        assert_eq!(Color { r: 178, g: 128, b: 228 }, col.lighten(50).to_color(&ctx));
+        assert_eq!(Color { r: 75, g: 0, b: 150 }, col.darken(25).to_color(&ctx));
+        assert_eq!(
+            Color { r: 90, g: 0, b: 180 },
+            col.mix(ColType::RGB(BLACK), 10).to_color(&ctx)
+        );
+        assert_eq!(Color { r: 178, g: 128, b: 228 }, col.alpha(50).to_color(&ctx));
+        assert_eq!(
+            PURPLE,
+            Col::from(ColType::Named("magenta".to_string())).to_color(&ctx)
+        );
+        assert_eq!(
+            Color { r: 77, g: 127, b: 27 },
+            col.lighten(50).inv().to_color(&ctx)
+        );
+    }
+
+    #[test]
+    fn contrast_works() {
+        let players = vec![
+            Player {
+                name: "Ann".to_string(),
+                color: AMBER,
+            },
+        ];
+        let ctx = Context::new(&players);
+        let fg = |bg: Color, children: Vec<N>| {
+            let input = vec![N::Bg(bg.into(), vec![N::Fg(Col::from(0).contrast(), children)])];
+            match transform_with(&input, &ctx)[0] {
+                TN::Bg(_, ref c) => match c[0] {
+                    TN::Fg(col, _) => col,
+                    _ => panic!("expected fg"),
+                },
+                _ => panic!("expected bg"),
+            }
+        };
+        assert_eq!(AMBER, fg(BLACK, vec![N::text("a")]));
+        let on_white = fg(WHITE, vec![N::text("a")]);
+        assert_ne!(AMBER, on_white);
+        assert!(color::contrast_ratio(&on_white, &WHITE) >= 4.5);
+        // Backgrounds inside the foreground are also taken into account.
+        let mixed = fg(WHITE, vec![N::text("a"), N::Bg(YELLOW.into(), vec![N::text("b")])]);
+        assert!(color::contrast_ratio(&mixed, &WHITE) >= 4.5);
+        assert!(color::contrast_ratio(&mixed, &YELLOW) >= 4.5);
+        assert_eq!(
+            fg(WHITE, vec![N::text("a")]),
+            Col::from(0).contrast().to_color(&ctx)
+        );
+    }
+
+    #[test]
+    fn contrast_on_tiles_works() {
+        let players = vec![
+            Player {
+                name: "Ann".to_string(),
+                color: AMBER,
+            },
+        ];
+        let ctx = Context::new(&players);
+        fn fgs(nodes: &[TN]) -> Vec<Color> {
+            nodes
+                .iter()
+                .flat_map(|n| match *n {
+                    TN::Fg(c, ref children) => {
+                        let mut cs = vec![c];
+                        cs.extend(fgs(children));
+                        cs
+                    }
+                    TN::Bg(_, ref children) | TN::Bold(ref children) => fgs(children),
+                    TN::Text(_) => vec![],
+                })
+                .collect()
+        }
+        let piece = || vec![N::Fg(Col::from(0).contrast(), vec![N::text("o")])];
+        // Board cells are read over their own light or dark background.
+        let board = fgs(&transform_with(
+            &[
+                N::Board(Board {
+                    rows: vec![vec![(A::Center, piece()), (A::Center, piece())]],
+                    cell_width: 1,
+                    cell_height: 1,
+                    light: WHITE.into(),
+                    dark: BLACK.into(),
+                    labels: BoardLabels::None,
+                }),
+            ],
+            &ctx,
+        ));
+        assert_eq!(2, board.len());
+        assert_ne!(AMBER, board[0]);
+        assert!(color::contrast_ratio(&board[0], &WHITE) >= 4.5);
+        assert_eq!(AMBER, board[1]);
+        // Hex content is read over the hex fill.
+        let hex = fgs(&transform_with(
+            &[
+                N::HexGrid(
+                    HexOrientation::Pointy,
+                    vec![
+                        Hex {
+                            col: 0,
+                            row: 0,
+                            fill: Some(BLACK.into()),
+                            border: None,
+                            content: piece(),
+                        },
+                    ],
+                ),
+            ],
+            &ctx,
+        ));
+        assert_eq!(vec![AMBER], hex);
+        let hex = fgs(&transform_with(
+            &[
+                N::HexGrid(
+                    HexOrientation::Pointy,
+                    vec![
+                        Hex {
+                            col: 0,
+                            row: 0,
+                            fill: Some(WHITE.into()),
+                            border: None,
+                            content: piece(),
+                        },
+                    ],
+                ),
+            ],
+            &ctx,
+        ));
+        assert!(color::contrast_ratio(&hex[0], &WHITE) >= 4.5);
+    }
+
+    #[test]
+    fn theme_works() {
+        let muted = Col::from(ColType::Theme(ThemeRole::Muted));
+        let danger = Col::from(ColType::Theme(ThemeRole::Danger));
+        let light = Context::new(&[]);
+        assert_eq!(GREY, muted.to_color(&light));
+        assert_eq!(RED, danger.to_color(&light));
+        let dark = Context::new(&[]).theme(Theme::dark());
+        assert_ne!(RED, danger.to_color(&dark));
+        assert_eq!(
+            Theme::dark().background,
+            Col::from(RED).alpha(0).to_color(&dark)
+        );
+        let text = Col::from(ColType::Theme(ThemeRole::Text));
+        for ctx in &[light, dark] {
+            assert!(color::contrast_ratio(&text.to_color(ctx), &ctx.theme.background) >= 4.5);
+        }
+    }
+
+    #[test]
+    fn color_blindness_works() {
+        let players = vec![
+            Player {
+                name: "Ann".to_string(),
+                color: RED,
+            },
+        ];
+        let cb = ColorBlindness::Deuteranopia;
+        let ctx = Context::new(&players).color_blindness(cb);
+        assert_eq!(
+            vec![
+                TN::Bg(
+                    color::daltonize(&GREEN, cb),
+                    vec![
+                        TN::Bold(vec![
+                            TN::Fg(color::daltonize(&RED, cb), vec![TN::text("<Ann>")]),
+                        ]),
+                    ],
+                ),
+            ],
+            transform_with(&[N::Bg(GREEN.into(), vec![N::Player(0)])], &ctx)
+        );
+    }
+
+    #[test]
+    fn gradient_works() {
+        let mid = color::mix(&RED, &BLUE, 50);
+        assert_eq!(
+            vec![
+                TN::Fg(RED, vec![TN::text("a")]),
+                TN::Fg(mid, vec![TN::Bold(vec![TN::text("b")])]),
+                TN::Fg(BLUE, vec![TN::Bold(vec![TN::text("c")])]),
+                TN::text("\n"),
+                TN::Fg(RED, vec![TN::text("d")]),
+            ],
+            transform(
+                &[
+                    N::Gradient(
+                        RED.into(),
+                        BLUE.into(),
+                        false,
+                        vec![N::text("a"), N::Bold(vec![N::text("bc")]), N::text("\nd")],
+                    ),
+                ],
+                &[]
+            )
+        );
+        assert_eq!(
+            vec![
+                TN::Bg(RED, vec![TN::text("ab")]),
+                TN::text("\n"),
+                TN::Bg(mid, vec![TN::text("c")]),
+                TN::text("\n"),
+                TN::Bg(BLUE, vec![TN::text("d")]),
+            ],
+            transform(
+                &[N::Gradient(RED.into(), BLUE.into(), true, vec![N::text("ab\nc\nd")])],
+                &[]
+            )
+        );
+    }
+
+    #[test]
+    fn team_works() {
+        let teams = vec![
+            Team {
+                name: "Team Red".to_string(),
+                color: RED,
+                members: vec![0, 2],
+            },
+            Team {
+                name: "Team Blue".to_string(),
+                color: BLUE,
+                members: vec![1, 3],
+            },
+        ];
+        let ctx = Context::new(&[]).teams(&teams);
+        assert_eq!(Some(1), ctx.team_of(3));
+        assert_eq!(None, ctx.team_of(4));
+        assert_eq!(
+            vec![
+                TN::Bold(vec![TN::Fg(RED, vec![TN::text("Team Red")])]),
+                TN::Fg(BLUE, vec![TN::text("x")]),
+            ],
+            transform_with(
+                &[
+                    N::Team(0),
+                    N::Fg(
+                        Col {
+                            color: ColType::Team(1),
+                            transform: vec![],
+                        },
+                        vec![N::text("x")],
+                    ),
+                ],
+                &ctx
+            )
+        );
+        assert_eq!("Team 2", render(&transform_with(&[N::Team(2)], &ctx)));
+    }
+
+    #[test]
+    fn player_style_works() {
+        let players = vec![
+            Player {
+                name: "Mick Jagger".to_string(),
+                color: GREEN,
+            },
+        ];
+        let refs = vec![
+            N::Player(0),
+            N::text(" "),
+            N::PlayerRef(PlayerRef::new(0).opt(PlayerOpt::Short)),
+            N::text(" "),
+            N::PlayerRef(PlayerRef::new(0).opt(PlayerOpt::Initials).opt(PlayerOpt::Plain)),
+            N::text(" "),
+            N::PlayerRef(PlayerRef::new(1).opt(PlayerOpt::Icon).possessive()),
+            N::text(" "),
+            N::PlayerRef(PlayerRef::new(1).opt(PlayerOpt::Short)),
+        ];
+        assert_eq!(
+            "<Mick Jagger> <Mic> MJ ■<Player 1>'s <Player 1>",
+            render(&transform(&refs, &players))
+        );
+        let ctx = Context::new(&players).player_style(PlayerStyle {
+            brackets: false,
+            bold: false,
+            icon: true,
+            name: NameFormat::Short(4),
+            fallback: "P{}".to_string(),
+        });
+        assert_eq!(
+            "●Mick ●Mick ●MJ ■P1's ■P1",
+            render(&transform_with(&refs, &ctx))
+        );
+        // The short option keeps a configured length.
+        let short_ctx = Context::new(&players).player_style(PlayerStyle {
+            name: NameFormat::Short(6),
+            ..PlayerStyle::default()
+        });
+        assert_eq!(
+            "<Mick J> <Mick J>",
+            render(&transform_with(&refs[..3], &short_ctx))
+        );
+        assert_eq!(
+            vec![TN::Fg(GREEN, vec![TN::text("●Mick")])],
+            transform_with(&[N::Player(0)], &ctx)
+        );
+    }
+
     #[test]
     fn to_lines_works() {
         assert_eq!(
@@ -471,5 +2122,10 @@ mod tests {
                 ]),
             ]
         );
+        // Nodes ending exactly at the start of the range are skipped entirely.
+        assert_eq!(
+            slice(&[TN::Fg(RED, vec![TN::text("ab")]), TN::text("cd")], &(2..4)),
+            vec![TN::text("cd")]
+        );
     }
 }